@@ -5,30 +5,32 @@ use std::time::Duration;
 
 use bevy::{
     core_pipeline::prepass::DepthPrepass,
-    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
-    pbr::ExtendedMaterial,
+    diagnostic::{
+        Diagnostic, DiagnosticPath, Diagnostics, LogDiagnosticsPlugin, RegisterDiagnostic,
+    },
     prelude::*,
 };
-use bevy_contact_projective_decals::{decal_mesh_quad,  DecalMaterial, DecalPlugin};
+use bevy_contact_projective_decals::{
+    Decal, DecalLifetime, DecalMaterial, DecalMaterialExtension, DecalPlugin,
+};
 use rand::{thread_rng, Rng};
 fn main() {
     App::new()
         .add_plugins((
             DefaultPlugins,
-            DecalPlugin,
+            DecalPlugin::default(),
             PanOrbitCameraPlugin,
             EguiPlugin,
-            
+            LogDiagnosticsPlugin::filtered(vec![DECAL_COUNT]),
         ))
         .register_diagnostic(Diagnostic::new(DECAL_COUNT))
-        .add_systems(Startup, (setup, setup_diagnostic))
+        .add_systems(Startup, setup)
         .add_systems(Update, (spawn_decals, thing_count))
         .run();
 }
 #[derive(Resource)]
 struct DecalHandles {
-    mat_uv: Handle<ExtendedMaterial<StandardMaterial, DecalMaterial>>,
-    mesh: Handle<Mesh>,
+    mat_uv: Handle<DecalMaterialExtension>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -56,12 +58,11 @@ fn spawn_decals(
         let x = thread_rng().gen_range(-5.0..5.0);
         let z = thread_rng().gen_range(-5.0..5.0);
         let scale = thread_rng().gen();
-        commands.spawn(DecalBundle {
-            transform: Transform::from_xyz(x, 0.0, z).with_scale(Vec3::splat(scale)),
-            decal_material: my_handles.mat_uv.clone(),
-            mesh: my_handles.mesh.clone(),
-            ..default()
-        });
+        commands.spawn((
+            Decal::new(my_handles.mat_uv.clone()),
+            Transform::from_xyz(x, 0.0, z).with_scale(Vec3::splat(scale)),
+            DecalLifetime::new(10.0, 2.0),
+        ));
     }
 }
 
@@ -70,10 +71,10 @@ fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut decal_materials: ResMut<Assets<ExtendedMaterial<StandardMaterial, DecalMaterial>>>,
+    mut decal_materials: ResMut<Assets<DecalMaterialExtension>>,
     asset_server: Res<AssetServer>,
 ) {
-    let decal_material = decal_materials.add(ExtendedMaterial::<StandardMaterial, DecalMaterial> {
+    let decal_material = decal_materials.add(DecalMaterialExtension {
         base: StandardMaterial {
             base_color_texture: Some(asset_server.load("UVCheckerMap01-512.png")),
             base_color: Color::WHITE,
@@ -81,26 +82,22 @@ fn setup(
             ..default()
         },
         extension: DecalMaterial {
-            depth_fade_factor: 8.0,
+            depth_fade_factor: Some(8.0),
+            ..default()
         },
     });
     commands.insert_resource(DecalHandles {
         mat_uv: decal_material,
-        mesh: meshes.add(decal_mesh_quad(Vec3::Y)),
-    });
-    commands.spawn((TextBundle::from_section(
-        "Press space to start / stop.\nDrag mouse to pan, scroll to zoom",
-        TextStyle {
-            font_size: 25.,
-            ..default()
-        },
-    ),));
-    commands.spawn(PbrBundle {
-        mesh: meshes.add(Rectangle::new(10.0, 10.0)),
-        material: materials.add(Color::WHITE),
-        transform: Transform::from_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
-        ..default()
     });
+    commands.spawn((
+        Text::new("Press space to start / stop.\nDrag mouse to pan, scroll to zoom"),
+        TextFont::from_font_size(25.0),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Rectangle::new(10.0, 10.0))),
+        MeshMaterial3d(materials.add(Color::WHITE)),
+        Transform::from_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
+    ));
 
     let num_obs = 10;
     for i in 0..num_obs {
@@ -117,47 +114,34 @@ fn setup(
                 rotation_vec.normalize_or_zero(),
                 (rotation as f32).to_radians(),
             ));
-            commands.spawn(PbrBundle {
-                mesh: meshes.add(Cuboid::new(0.6, 0.6, 0.6)),
-                material: materials.add(Color::WHITE),
+            commands.spawn((
+                Mesh3d(meshes.add(Cuboid::new(0.6, 0.6, 0.6))),
+                MeshMaterial3d(materials.add(Color::WHITE)),
                 transform,
-                ..default()
-            });
+            ));
         }
     }
     // light
-    commands.spawn(PointLightBundle {
-        point_light: PointLight {
+    commands.spawn((
+        PointLight {
             intensity: 10000000.0,
             range: 60.0,
             radius: 60.0,
             shadows_enabled: true,
             ..default()
         },
-        transform: Transform::from_xyz(14.0, 8.0, 4.0),
-        ..default()
-    });
+        Transform::from_xyz(14.0, 8.0, 4.0),
+    ));
     // camera
     commands.spawn((
-        Camera3dBundle {
-            transform: Transform::from_xyz(2.0, 9.5, 2.5).looking_at(Vec3::ZERO, Vec3::Y),
-            ..default()
-        },
+        Camera3d::default(),
+        Transform::from_xyz(2.0, 9.5, 2.5).looking_at(Vec3::ZERO, Vec3::Y),
         PanOrbitCamera::default(),
         DepthPrepass,
     ));
 }
 const DECAL_COUNT: DiagnosticPath = DiagnosticPath::const_new("decal_count");
-fn setup_diagnostic(mut onscreen: ResMut<ScreenDiagnostics>) {
-    onscreen
-        .add("decals".to_string(), DECAL_COUNT)
-        .aggregate(Aggregate::Value)
-        .format(|v| format!("{v:.0}"));
-}
 
-fn thing_count(
-    mut diagnostics: Diagnostics,
-    parts: Query<&Handle<ExtendedMaterial<StandardMaterial, DecalMaterial>>>,
-) {
+fn thing_count(mut diagnostics: Diagnostics, parts: Query<&Decal>) {
     diagnostics.add_measurement(&DECAL_COUNT, || parts.iter().len() as f64);
 }
@@ -1,14 +1,14 @@
 use bevy::{
     core_pipeline::prepass::DepthPrepass,
-    pbr::{ExtendedMaterial, NotShadowCaster, NotShadowReceiver},
+    pbr::{NotShadowCaster, NotShadowReceiver},
     prelude::*,
 };
-use bevy_contact_projective_decals::{decal_mesh_quad,  DecalMaterial, DecalPlugin};
+use bevy_contact_projective_decals::{DecalCommandsExt, DecalEntityCommandsExt, DecalPlugin};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_panorbit_camera::{PanOrbitCamera, PanOrbitCameraPlugin};
 fn main() {
     App::new()
-        .add_plugins((DefaultPlugins, DecalPlugin, PanOrbitCameraPlugin))
+        .add_plugins((DefaultPlugins, DecalPlugin::default(), PanOrbitCameraPlugin))
         .add_plugins(WorldInspectorPlugin::new())
         .add_systems(Startup, setup)
         .add_systems(Update, move_camera)
@@ -32,169 +32,78 @@ fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut decal_materials: ResMut<Assets<ExtendedMaterial<StandardMaterial, DecalMaterial>>>,
     asset_server: Res<AssetServer>,
 ) {
     commands.spawn((
         NotShadowCaster,
         NotShadowReceiver,
-        PbrBundle {
-            mesh: meshes.add(Rectangle::new(10.0, 10.0)),
-            material: materials.add(Color::WHITE),
-            transform: Transform::from_rotation(Quat::from_rotation_x(
-                -std::f32::consts::FRAC_PI_2,
-            )),
-            ..default()
-        },
+        Mesh3d(meshes.add(Rectangle::new(10.0, 10.0))),
+        MeshMaterial3d(materials.add(Color::WHITE)),
+        Transform::from_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
     ));
     commands.spawn((
         NotShadowCaster,
         NotShadowReceiver,
-        PbrBundle {
-            mesh: meshes.add(Cuboid::new(5.0, 5.0, 0.2)),
-            material: materials.add(Color::WHITE),
-            transform: Transform::from_xyz(0.0, 0.0, -2.5),
-            ..Default::default()
-        },
+        Mesh3d(meshes.add(Cuboid::new(5.0, 5.0, 0.2))),
+        MeshMaterial3d(materials.add(Color::WHITE)),
+        Transform::from_xyz(0.0, 0.0, -2.5),
     ));
 
     commands.spawn((
         NotShadowCaster,
         NotShadowReceiver,
-        PbrBundle {
-            mesh: meshes.add(Cuboid::new(5.0, 5.0, 0.2)),
-            material: materials.add(Color::WHITE),
-            transform: Transform::from_xyz(0.0, 0.0, 2.5),
-            ..Default::default()
-        },
+        Mesh3d(meshes.add(Cuboid::new(5.0, 5.0, 0.2))),
+        MeshMaterial3d(materials.add(Color::WHITE)),
+        Transform::from_xyz(0.0, 0.0, 2.5),
     ));
 
     commands.spawn((
         NotShadowCaster,
         NotShadowReceiver,
-        PbrBundle {
-            mesh: meshes.add(Cuboid::new(0.2, 5.0, 5.0)),
-            material: materials.add(Color::WHITE),
-            transform: Transform::from_xyz(-2.5, 0.0, 0.0),
-            ..Default::default()
-        },
+        Mesh3d(meshes.add(Cuboid::new(0.2, 5.0, 5.0))),
+        MeshMaterial3d(materials.add(Color::WHITE)),
+        Transform::from_xyz(-2.5, 0.0, 0.0),
     ));
 
     commands.spawn((
         NotShadowCaster,
         NotShadowReceiver,
-        PbrBundle {
-            mesh: meshes.add(Cuboid::new(0.2, 5.0, 5.0)),
-            material: materials.add(Color::WHITE),
-            transform: Transform::from_xyz(2.5, 0.0, 0.0),
-            ..Default::default()
-        },
+        Mesh3d(meshes.add(Cuboid::new(0.2, 5.0, 5.0))),
+        MeshMaterial3d(materials.add(Color::WHITE)),
+        Transform::from_xyz(2.5, 0.0, 0.0),
     ));
     // light
-    commands.spawn(PointLightBundle {
-        point_light: PointLight {
+    commands.spawn((
+        PointLight {
             intensity: 1000000.0,
             range: 60.0,
             radius: 60.0,
             shadows_enabled: true,
             ..default()
         },
-        transform: Transform::from_xyz(0.0, 10.0, 0.0),
-        ..default()
-    });
-
-    commands.spawn(DecalBundle {
-        transform: Transform::from_xyz(0.0, 0.0, 0.0),
-        decal_material: decal_materials.add(ExtendedMaterial::<StandardMaterial, DecalMaterial> {
-            base: StandardMaterial {
-                base_color_texture: Some(asset_server.load("blast.png")),
-                base_color: Color::Srgba(Srgba::RED),
-                alpha_mode: AlphaMode::Blend,
-                ..default()
-            },
-            extension: DecalMaterial {
-                depth_fade_factor: 8.0,
-            },
-        }),
-        mesh: meshes.add(decal_mesh_quad(Vec3::Y)),
-        ..default()
-    });
-
-    commands.spawn(DecalBundle {
-        transform: Transform::from_xyz(-2.4, 1.0, 0.0),
-        decal_material: decal_materials.add(ExtendedMaterial::<StandardMaterial, DecalMaterial> {
-            base: StandardMaterial {
-                base_color_texture: Some(asset_server.load("blast.png")),
-                base_color: Color::Srgba(Srgba::RED),
-                alpha_mode: AlphaMode::Blend,
-                ..default()
-            },
-            extension: DecalMaterial {
-                depth_fade_factor: 8.0,
-            },
-        }),
-        mesh: meshes.add(decal_mesh_quad(Vec3::X)),
-        ..default()
-    });
-
-    commands.spawn(DecalBundle {
-        transform: Transform::from_xyz(2.4, 1.0, 0.0),
-        decal_material: decal_materials.add(ExtendedMaterial::<StandardMaterial, DecalMaterial> {
-            base: StandardMaterial {
-                base_color_texture: Some(asset_server.load("blast.png")),
-                base_color: Color::Srgba(Srgba::RED),
-                alpha_mode: AlphaMode::Blend,
-                ..default()
-            },
-            extension: DecalMaterial {
-                depth_fade_factor: 8.0,
-            },
-        }),
-        mesh: meshes.add(decal_mesh_quad(-Vec3::X)),
-        ..default()
-    });
+        Transform::from_xyz(0.0, 10.0, 0.0),
+    ));
 
-    commands.spawn(DecalBundle {
-        transform: Transform::from_xyz(0.0, 1.0, 2.4),
-        decal_material: decal_materials.add(ExtendedMaterial::<StandardMaterial, DecalMaterial> {
-            base: StandardMaterial {
-                base_color_texture: Some(asset_server.load("blast.png")),
-                base_color: Color::Srgba(Srgba::RED),
-                alpha_mode: AlphaMode::Blend,
-                ..default()
-            },
-            extension: DecalMaterial {
-                depth_fade_factor: 8.0,
-            },
-        }),
-        mesh: meshes.add(decal_mesh_quad(-Vec3::Z)),
-        ..default()
-    });
+    let blast = asset_server.load("blast.png");
 
-    commands.spawn(DecalBundle {
-        transform: Transform::from_xyz(0.0, 1.0, -2.4),
-        decal_material: decal_materials.add(ExtendedMaterial::<StandardMaterial, DecalMaterial> {
-            base: StandardMaterial {
-                base_color_texture: Some(asset_server.load("blast.png")),
-                base_color: Color::Srgba(Srgba::RED),
-                alpha_mode: AlphaMode::Blend,
-                ..default()
-            },
-            extension: DecalMaterial {
-                depth_fade_factor: 8.0,
-            },
-        }),
-        mesh: meshes.add(decal_mesh_quad(Vec3::Z)),
-        ..default()
-    });
+    for (position, normal) in [
+        (Vec3::new(0.0, 0.0, 0.0), Vec3::Y),
+        (Vec3::new(-2.4, 1.0, 0.0), Vec3::X),
+        (Vec3::new(2.4, 1.0, 0.0), -Vec3::X),
+        (Vec3::new(0.0, 1.0, 2.4), -Vec3::Z),
+        (Vec3::new(0.0, 1.0, -2.4), Vec3::Z),
+    ] {
+        commands
+            .spawn_decal(blast.clone())
+            .at(position)
+            .facing(normal)
+            .tint(Color::Srgba(Srgba::RED));
+    }
 
     // camera
     commands.spawn((
-        Camera3dBundle {
-            transform: Transform::from_xyz(1.0, 6.0, 0.0)
-                .looking_at(Vec3::new(0.0, 6.0, 0.0), Vec3::Y),
-            ..default()
-        },
+        Camera3d::default(),
+        Transform::from_xyz(1.0, 6.0, 0.0).looking_at(Vec3::new(0.0, 6.0, 0.0), Vec3::Y),
         PanOrbitCamera::default(),
         DepthPrepass,
     ));
@@ -8,7 +8,7 @@ fn main() {
     App::new()
         .add_plugins((
             DefaultPlugins,
-            DecalPlugin,
+            DecalPlugin::default(),
             PanOrbitCameraPlugin,
             WorldInspectorPlugin::default(),
         ))
@@ -201,7 +201,8 @@ fn setup(
                             ..default()
                         },
                         extension: DecalMaterial {
-                            depth_fade_factor: 8.0,
+                            depth_fade_factor: Some(8.0),
+                            ..default()
                         },
                     })
 
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::{Decal, DecalLifetime};
+
+type SharedCurve<T> = Arc<dyn Curve<T> + Send + Sync>;
+
+/// Animates a [`Decal`] over its normalized age, from 0 when spawned to 1 at the end.
+///
+/// The age follows the entity's [`DecalLifetime`] unless a duration is set with
+/// [`DecalAnimation::with_duration`]. Curves are sampled clamped to their domain, so curves over
+/// `0.0..=1.0` map exactly onto the age. Tint and opacity only affect this entity, scale and
+/// rotation are applied on top of the [`Transform`] it had when the animation started.
+///
+/// ```rs
+/// DecalAnimation::new()
+///     .with_scale(EasingCurve::new(0.0, 1.0, EaseFunction::BackOut))
+///     .with_tint(EasingCurve::new(
+///         LinearRgba::rgb(4.0, 1.5, 0.2),
+///         LinearRgba::rgb(0.1, 0.1, 0.1),
+///         EaseFunction::QuadraticOut,
+///     ))
+/// ```
+#[derive(Component, Clone, Default)]
+pub struct DecalAnimation {
+    /// Uniform scale multiplier.
+    pub scale: Option<SharedCurve<f32>>,
+    /// Linear color multiplied onto the material's base color.
+    pub tint: Option<SharedCurve<LinearRgba>>,
+    /// Rotation about [`Decal::normal`] in radians.
+    pub rotation: Option<SharedCurve<f32>>,
+    /// Opacity multiplier.
+    pub opacity: Option<SharedCurve<f32>>,
+    /// Seconds the animation takes, `None` follows the [`DecalLifetime`].
+    pub duration: Option<f32>,
+    elapsed: f32,
+    base: Option<Transform>,
+}
+impl DecalAnimation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_scale(mut self, curve: impl Curve<f32> + Send + Sync + 'static) -> Self {
+        self.scale = Some(Arc::new(curve));
+        self
+    }
+    pub fn with_tint(mut self, curve: impl Curve<LinearRgba> + Send + Sync + 'static) -> Self {
+        self.tint = Some(Arc::new(curve));
+        self
+    }
+    pub fn with_rotation(mut self, curve: impl Curve<f32> + Send + Sync + 'static) -> Self {
+        self.rotation = Some(Arc::new(curve));
+        self
+    }
+    pub fn with_opacity(mut self, curve: impl Curve<f32> + Send + Sync + 'static) -> Self {
+        self.opacity = Some(Arc::new(curve));
+        self
+    }
+    pub fn with_duration(mut self, duration: f32) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Normalized age, from the own duration if set or else from `lifetime`.
+    pub fn age(&self, lifetime: Option<&DecalLifetime>) -> f32 {
+        match (self.duration, lifetime) {
+            (Some(duration), _) if duration > 0.0 => (self.elapsed / duration).clamp(0.0, 1.0),
+            (Some(_), _) => 1.0,
+            (None, Some(lifetime)) => lifetime.age(),
+            (None, None) => 0.0,
+        }
+    }
+    pub fn tint(&self, age: f32) -> LinearRgba {
+        self.tint
+            .as_ref()
+            .map_or(LinearRgba::WHITE, |curve| curve.sample_clamped(age))
+    }
+    pub fn opacity(&self, age: f32) -> f32 {
+        self.opacity
+            .as_ref()
+            .map_or(1.0, |curve| curve.sample_clamped(age))
+    }
+}
+
+pub(crate) fn animate_decals(
+    mut decals: Query<(
+        &mut DecalAnimation,
+        &mut Transform,
+        &Decal,
+        Option<&DecalLifetime>,
+    )>,
+    time: Res<Time>,
+) {
+    for (mut animation, mut transform, decal, lifetime) in &mut decals {
+        animation.elapsed += time.delta_secs();
+        let base = *animation.base.get_or_insert(*transform);
+        let age = animation.age(lifetime);
+        if let Some(scale) = &animation.scale {
+            transform.scale = base.scale * scale.sample_clamped(age);
+        }
+        if let Some(rotation) = &animation.rotation {
+            let axis = decal.normal.try_normalize().unwrap_or(Vec3::Y);
+            transform.rotation =
+                base.rotation * Quat::from_axis_angle(axis, rotation.sample_clamped(age));
+        }
+    }
+}
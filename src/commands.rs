@@ -0,0 +1,163 @@
+use bevy::{ecs::system::EntityCommands, platform::collections::HashMap, prelude::*};
+
+use crate::{Decal, DecalInstanceParams, DecalMaterial, DecalMaterialExtension};
+
+/// Texture of a decal spawned with [`DecalCommandsExt::spawn_decal`].
+///
+/// Decals with the same appearance share one material, tint and fade are set per entity through
+/// [`DecalInstanceParams`].
+#[derive(Component, Clone, Debug, PartialEq)]
+#[require(Decal, DecalInstanceParams)]
+pub struct DecalAppearance {
+    pub texture: Handle<Image>,
+    /// Skips lighting, see [`DecalMaterial::unlit`].
+    pub unlit: bool,
+}
+impl DecalAppearance {
+    pub fn new(texture: Handle<Image>) -> Self {
+        Self {
+            texture,
+            unlit: false,
+        }
+    }
+}
+
+/// Materials created for [`DecalAppearance`]s, one per texture and lighting.
+#[derive(Resource, Default)]
+pub(crate) struct DecalMaterialCache(
+    HashMap<(AssetId<Image>, bool), Handle<DecalMaterialExtension>>,
+);
+
+pub(crate) fn resolve_decal_appearance(
+    mut decals: Query<(&DecalAppearance, &mut Decal), Changed<DecalAppearance>>,
+    mut cache: ResMut<DecalMaterialCache>,
+    mut materials: ResMut<Assets<DecalMaterialExtension>>,
+) {
+    for (appearance, mut decal) in &mut decals {
+        let key = (appearance.texture.id(), appearance.unlit);
+        let material = cache.0.entry(key).or_insert_with(|| {
+            materials.add(DecalMaterialExtension {
+                base: StandardMaterial {
+                    base_color_texture: Some(appearance.texture.clone()),
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                },
+                extension: DecalMaterial {
+                    unlit: appearance.unlit,
+                    ..default()
+                },
+            })
+        });
+        if decal.material != *material {
+            decal.material = material.clone();
+        }
+    }
+}
+
+/// Spawns decals from [`Commands`].
+pub trait DecalCommandsExt {
+    /// Spawns a decal showing `texture`, configure it further with [`DecalEntityCommandsExt`].
+    ///
+    /// ```rs
+    /// commands
+    ///     .spawn_decal(asset_server.load("blast.png"))
+    ///     .at(Vec3::new(0.0, 0.0, 2.0))
+    ///     .facing(Vec3::Y)
+    ///     .size(Vec2::splat(2.0))
+    ///     .tint(Color::Srgba(Srgba::RED))
+    ///     .fade(4.0);
+    /// ```
+    fn spawn_decal(&mut self, texture: Handle<Image>) -> EntityCommands<'_>;
+}
+impl DecalCommandsExt for Commands<'_, '_> {
+    fn spawn_decal(&mut self, texture: Handle<Image>) -> EntityCommands<'_> {
+        self.spawn(DecalAppearance::new(texture))
+    }
+}
+
+/// Fluent setters for decals spawned with [`DecalCommandsExt::spawn_decal`].
+pub trait DecalEntityCommandsExt {
+    /// Moves the decal to `position`.
+    fn at(&mut self, position: Vec3) -> &mut Self;
+    /// Sets [`Decal::normal`], the direction the decal projects away from.
+    fn facing(&mut self, normal: Vec3) -> &mut Self;
+    /// Sets [`Decal::size`].
+    fn size(&mut self, size: Vec2) -> &mut Self;
+    /// Sets [`DecalInstanceParams::tint`].
+    fn tint(&mut self, tint: Color) -> &mut Self;
+    /// Sets [`DecalInstanceParams::opacity`].
+    fn opacity(&mut self, opacity: f32) -> &mut Self;
+    /// Sets [`DecalInstanceParams::fade_factor`].
+    fn fade(&mut self, fade: f32) -> &mut Self;
+    /// Sets [`DecalInstanceParams::atlas_index`].
+    fn atlas_index(&mut self, index: u32) -> &mut Self;
+    /// Sets [`DecalInstanceParams::receivers`].
+    fn receivers(&mut self, groups: u32) -> &mut Self;
+    /// Sets [`DecalAppearance::unlit`].
+    fn unlit(&mut self) -> &mut Self;
+}
+impl DecalEntityCommandsExt for EntityCommands<'_> {
+    fn at(&mut self, position: Vec3) -> &mut Self {
+        self.queue(move |mut entity: EntityWorldMut| {
+            if let Some(mut transform) = entity.get_mut::<Transform>() {
+                transform.translation = position;
+            }
+        })
+    }
+    fn facing(&mut self, normal: Vec3) -> &mut Self {
+        self.queue(move |mut entity: EntityWorldMut| {
+            if let Some(mut decal) = entity.get_mut::<Decal>() {
+                decal.normal = normal;
+            }
+        })
+    }
+    fn size(&mut self, size: Vec2) -> &mut Self {
+        self.queue(move |mut entity: EntityWorldMut| {
+            if let Some(mut decal) = entity.get_mut::<Decal>() {
+                decal.size = size;
+            }
+        })
+    }
+    fn tint(&mut self, tint: Color) -> &mut Self {
+        self.queue(move |mut entity: EntityWorldMut| {
+            if let Some(mut params) = entity.get_mut::<DecalInstanceParams>() {
+                params.tint = tint;
+            }
+        })
+    }
+    fn opacity(&mut self, opacity: f32) -> &mut Self {
+        self.queue(move |mut entity: EntityWorldMut| {
+            if let Some(mut params) = entity.get_mut::<DecalInstanceParams>() {
+                params.opacity = opacity;
+            }
+        })
+    }
+    fn fade(&mut self, fade: f32) -> &mut Self {
+        self.queue(move |mut entity: EntityWorldMut| {
+            if let Some(mut params) = entity.get_mut::<DecalInstanceParams>() {
+                params.fade_factor = Some(fade);
+            }
+        })
+    }
+    fn atlas_index(&mut self, index: u32) -> &mut Self {
+        self.queue(move |mut entity: EntityWorldMut| {
+            if let Some(mut params) = entity.get_mut::<DecalInstanceParams>() {
+                params.atlas_index = Some(index);
+            }
+        })
+    }
+    fn receivers(&mut self, groups: u32) -> &mut Self {
+        self.queue(move |mut entity: EntityWorldMut| {
+            if let Some(mut params) = entity.get_mut::<DecalInstanceParams>() {
+                params.receivers = groups;
+            }
+        })
+    }
+    fn unlit(&mut self) -> &mut Self {
+        self.queue(|mut entity: EntityWorldMut| {
+            if let Some(mut appearance) = entity.get_mut::<DecalAppearance>() {
+                appearance.unlit = true;
+            }
+        })
+    }
+}
@@ -1,67 +1,339 @@
 use bevy::{
     asset::embedded_asset,
-    math::primitives::Rectangle,
+    core_pipeline::core_3d::{
+        graph::{Core3d, Node3d},
+        Transparent3d,
+    },
     pbr::{
         ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline,
-        NotShadowCaster,
+        MeshPipeline, NotShadowCaster, NotShadowReceiver,
     },
     prelude::*,
     render::{
-        mesh::MeshVertexBufferLayoutRef,
+        extract_component::ExtractComponentPlugin,
+        mesh::{MeshTag, MeshVertexBufferLayoutRef},
+        primitives::Aabb,
+        render_graph::{RenderGraphApp, ViewNodeRunner},
+        render_phase::{
+            sort_phase_system, AddRenderCommand, DrawFunctions, SortedRenderPhasePlugin,
+        },
         render_resource::{
-            AsBindGroup, CompareFunction, RenderPipelineDescriptor, ShaderRef,
-            SpecializedMeshPipelineError,
+            AsBindGroup, BlendComponent, BlendFactor, BlendOperation, BlendState, CompareFunction,
+            RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+            SpecializedMeshPipelines,
         },
+        storage::ShaderStorageBuffer,
+        view::VisibilitySystems,
+        Render, RenderApp, RenderDebugFlags, RenderSet,
     },
 };
 
-pub struct DecalPlugin;
+#[cfg(not(feature = "webgl2"))]
+use crate::instance::{extract_decal_instances, write_decal_instance_buffer, DecalInstanceWrites};
+use crate::{
+    animation::animate_decals,
+    commands::{resolve_decal_appearance, DecalMaterialCache},
+    flipbook::{advance_decal_flipbooks, DecalFlipbook},
+    instance::{
+        assign_decal_instances, write_decal_instances, DecalInstanceParams, DecalInstances,
+        DECAL_INSTANCES_HANDLE,
+    },
+    layer::{extract_decal_draw_order, sequence_decals, sort_decals, DecalDrawOrder, DecalLayer},
+    lifetime::{tick_decal_lifetimes, DecalExpired, DecalLifetime},
+    mesh::{DecalMeshCache, DecalQuadBuilder},
+    pool::{prune_decal_pool, DecalPool},
+    prepass::manage_depth_prepass,
+    receiver::{
+        decal_receiver_mask, extract_decal_receiver_phases, queue_decal_receivers, DecalReceiver,
+        DecalReceiver3d, DecalReceiverMaskDirty, DecalReceiverNode, DecalReceiverPass,
+        DecalReceiverPipeline, DrawDecalReceiver, NoDecals, DECAL_RECEIVER_MASK_HANDLE,
+    },
+    settings::{
+        apply_decal_shadow_settings, enforce_max_decals, DecalSettings, DecalSpawnOrder,
+        DEFAULT_FADE_FACTOR,
+    },
+    shader_types::DecalMaterialUniform,
+};
+
+/// Adds decal rendering, fields are copied into the [`DecalSettings`] resource.
+#[derive(Debug, Clone)]
+pub struct DecalPlugin {
+    /// See [`DecalSettings::default_fade_factor`].
+    pub default_fade_factor: f32,
+    /// See [`DecalSettings::auto_depth_prepass`].
+    pub auto_depth_prepass: bool,
+    /// See [`DecalSettings::shadows`].
+    pub shadows: bool,
+    /// See [`DecalSettings::max_decals`].
+    pub max_decals: Option<usize>,
+    /// How many decals can have per-entity data like a [`DecalInstanceParams`] or a
+    /// [`DecalFlipbook`] at once. The storage buffer holding it is allocated once with this many
+    /// slots, decals past it render with their material's values only.
+    ///
+    /// Default is 16384, which takes 768 KiB of GPU memory.
+    pub instance_capacity: u32,
+    /// Resolution of the [`DecalReceiver`] mask, allocated once and shared by every camera.
+    /// Targets larger than it get blockier receiver borders.
+    ///
+    /// Default is 1920x1080, which takes 8 MiB of GPU memory.
+    pub receiver_mask_size: UVec2,
+}
+impl Default for DecalPlugin {
+    fn default() -> Self {
+        let settings = DecalSettings::default();
+        Self {
+            default_fade_factor: settings.default_fade_factor,
+            auto_depth_prepass: settings.auto_depth_prepass,
+            shadows: settings.shadows,
+            max_decals: settings.max_decals,
+            instance_capacity: 16384,
+            receiver_mask_size: UVec2::new(1920, 1080),
+        }
+    }
+}
 impl Plugin for DecalPlugin {
     fn build(&self, app: &mut App) {
         embedded_asset!(app, "decal.wgsl");
-        app.add_plugins(
+        embedded_asset!(app, "decal_receiver.wgsl");
+        app.insert_resource(DecalSettings {
+            default_fade_factor: self.default_fade_factor,
+            auto_depth_prepass: self.auto_depth_prepass,
+            shadows: self.shadows,
+            max_decals: self.max_decals,
+        });
+        app.add_plugins((
             MaterialPlugin::<ExtendedMaterial<StandardMaterial, DecalMaterial>> {
                 prepass_enabled: false,
                 ..default()
             },
+            ExtractComponentPlugin::<DecalReceiver>::default(),
+            ExtractComponentPlugin::<NoDecals>::default(),
+            SortedRenderPhasePlugin::<DecalReceiver3d, MeshPipeline>::new(
+                RenderDebugFlags::default(),
+            ),
+        ))
+        .register_type::<Decal>()
+        .register_type::<DecalSettings>()
+        .register_type::<DecalFlipbook>()
+        .register_type::<DecalLifetime>()
+        .register_type::<DecalInstanceParams>()
+        .register_type::<DecalLayer>()
+        .register_type::<DecalReceiver>()
+        .register_type::<NoDecals>()
+        .add_event::<DecalExpired>()
+        .init_resource::<DecalMeshCache>()
+        .init_resource::<DecalMaterialCache>()
+        .init_resource::<DecalSpawnOrder>()
+        .insert_resource(DecalInstances::new(self.instance_capacity))
+        .init_resource::<DecalPool>()
+        .add_systems(
+            PostUpdate,
+            (
+                (resolve_decal_appearance, sync_decal_mesh_material)
+                    .chain()
+                    .before(VisibilitySystems::CalculateBounds),
+                apply_decal_shadow_settings,
+                enforce_max_decals,
+                manage_depth_prepass,
+                sequence_decals,
+                (
+                    assign_decal_instances,
+                    advance_decal_flipbooks,
+                    tick_decal_lifetimes,
+                    animate_decals,
+                    write_decal_instances,
+                    // After every system despawning decals, so the pool never recycles one.
+                    prune_decal_pool.after(enforce_max_decals),
+                )
+                    .chain()
+                    // Animated transforms are propagated the same frame as their tint and opacity.
+                    .before(TransformSystem::TransformPropagate),
+            ),
         );
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        // WebGL2 has no storage buffers, decals there only use their material's values.
+        #[cfg(not(feature = "webgl2"))]
+        render_app
+            .init_resource::<DecalInstanceWrites>()
+            .add_systems(ExtractSchedule, extract_decal_instances)
+            .add_systems(
+                Render,
+                write_decal_instance_buffer.in_set(RenderSet::PrepareResources),
+            );
+        render_app
+            .init_resource::<DecalDrawOrder>()
+            .init_resource::<DecalReceiverMaskDirty>()
+            .init_resource::<SpecializedMeshPipelines<DecalReceiverPipeline>>()
+            .init_resource::<DrawFunctions<DecalReceiver3d>>()
+            .add_render_command::<DecalReceiver3d, DrawDecalReceiver>()
+            .add_systems(
+                ExtractSchedule,
+                (extract_decal_draw_order, extract_decal_receiver_phases),
+            )
+            .add_systems(
+                Render,
+                (
+                    queue_decal_receivers.in_set(RenderSet::QueueMeshes),
+                    sort_phase_system::<DecalReceiver3d>.in_set(RenderSet::PhaseSort),
+                    sort_decals
+                        .in_set(RenderSet::PhaseSort)
+                        .after(sort_phase_system::<Transparent3d>),
+                ),
+            )
+            .add_render_graph_node::<ViewNodeRunner<DecalReceiverNode>>(Core3d, DecalReceiverPass)
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::EndPrepasses,
+                    DecalReceiverPass,
+                    Node3d::StartMainPass,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(mut images) = app.world_mut().get_resource_mut::<Assets<Image>>() {
+            images.insert(
+                &DECAL_RECEIVER_MASK_HANDLE,
+                decal_receiver_mask(self.receiver_mask_size),
+            );
+        }
+        #[cfg(not(feature = "webgl2"))]
+        {
+            let buffer = app.world().resource::<DecalInstances>().buffer();
+            if let Some(mut buffers) = app
+                .world_mut()
+                .get_resource_mut::<Assets<ShaderStorageBuffer>>()
+            {
+                buffers.insert(&DECAL_INSTANCES_HANDLE, buffer);
+            }
+        }
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        let pipeline = DecalReceiverPipeline::new(render_app.world_mut(), self.receiver_mask_size);
+        render_app.insert_resource(pipeline);
+    }
+}
+/// A contact projective decal.
+///
+/// Spawning this brings in everything a decal needs to render: a [`Mesh3d`] pointing at a quad
+/// from the [`DecalMeshCache`], a [`DecalMeshMaterial3d`] mirroring
+/// `material`, [`Visibility`], [`NotShadowCaster`] and [`NotShadowReceiver`].
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component, Default)]
+#[require(
+    Mesh3d,
+    DecalMeshMaterial3d,
+    MeshTag,
+    Visibility,
+    NotShadowCaster,
+    NotShadowReceiver
+)]
+pub struct Decal {
+    /// Size of the quad in local units, before the entity's [`Transform`] is applied.
+    pub size: Vec2,
+    /// Direction the decal projects away from, in the entity's local space.
+    pub normal: Vec3,
+    /// Direction of the top edge of the texture, see [`DecalQuadBuilder::up`].
+    pub up: Option<Vec3>,
+    /// Point of the quad placed at the entity's origin, see [`DecalQuadBuilder::pivot`].
+    pub pivot: Vec2,
+    /// Part of the texture shown on the decal, in uv coordinates.
+    pub uv_rect: Rect,
+    /// Mirrors the texture horizontally.
+    pub flip_x: bool,
+    /// Mirrors the texture vertically.
+    pub flip_y: bool,
+    pub material: Handle<DecalMaterialExtension>,
+}
+impl Default for Decal {
+    fn default() -> Self {
+        Self {
+            size: Vec2::ONE,
+            normal: Vec3::Y,
+            up: None,
+            pivot: Vec2::ZERO,
+            uv_rect: Rect::new(0.0, 0.0, 1.0, 1.0),
+            flip_x: false,
+            flip_y: false,
+            material: Handle::default(),
+        }
+    }
+}
+impl Decal {
+    pub fn new(material: Handle<DecalMaterialExtension>) -> Self {
+        Self {
+            material,
+            ..default()
+        }
+    }
+    pub fn with_size(mut self, size: Vec2) -> Self {
+        self.size = size;
+        self
+    }
+    pub fn with_normal(mut self, normal: Vec3) -> Self {
+        self.normal = normal;
+        self
+    }
+    pub fn with_up(mut self, up: Vec3) -> Self {
+        self.up = Some(up);
+        self
+    }
+    pub fn with_pivot(mut self, pivot: Vec2) -> Self {
+        self.pivot = pivot;
+        self
+    }
+    pub fn with_uv_rect(mut self, uv_rect: Rect) -> Self {
+        self.uv_rect = uv_rect;
+        self
+    }
+    pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
     }
 }
-/// A quad with specified size, rotated so that normal is facing Vec3::Y and generated tangents.
-pub fn decal_mesh_quad(normal: Vec3) -> Mesh {
-    Rectangle::from_size(Vec2::ONE)
-        .mesh()
-        .build()
-        .rotated_by(Quat::from_rotation_arc(Vec3::Z, normal))
-        .with_generated_tangents()
-        .unwrap()
-}
-/// Bundle containing what you need for a bundle, use [decal_mesh_quad] to generate the mesh.
-/*#[derive(Bundle, Default)]
-pub struct DecalBundle {
-    pub visibility: Visibility,
-    pub inherited_visibility: InheritedVisibility,
-    pub view_visibility: ViewVisibility,
-    pub transform: Transform,
-    pub global_transform: GlobalTransform,
-    pub decal_material: Handle<ExtendedMaterial<StandardMaterial, DecalMaterial>>,
-    /// mesh should preferably be generated by [decal_mesh_quad].
-    pub mesh: Handle<Mesh>,
-    pub not_shadow_caster: NotShadowCaster,
-}*/
-
-/*
-#[derive(Component )]
-#[require( NotShadowCaster )]
-pub struct DecalMaterialComponent (  pub Handle<ExtendedMaterial<StandardMaterial, DecalMaterial>>  ) ;
-*/
 
+fn sync_decal_mesh_material(
+    mut commands: Commands,
+    mut decals: Query<(Entity, &Decal, &mut Mesh3d, &mut DecalMeshMaterial3d), Changed<Decal>>,
+    mut cache: ResMut<DecalMeshCache>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (entity, decal, mut mesh, mut material) in &mut decals {
+        let quad = DecalQuadBuilder {
+            normal: decal.normal,
+            up: decal.up,
+            size: decal.size,
+            pivot: decal.pivot,
+            uv_rect: decal.uv_rect,
+            flip_x: decal.flip_x,
+            flip_y: decal.flip_y,
+        };
+        let quad = match cache.get_or_insert(quad, &mut meshes) {
+            Ok(quad) => quad,
+            Err(error) => {
+                warn!("Decal {entity}: {error}");
+                continue;
+            }
+        };
+        if mesh.0 != quad {
+            mesh.0 = quad;
+            // Bounds are only computed for meshes without an `Aabb`.
+            commands.entity(entity).remove::<Aabb>();
+        }
+        if material.0 != decal.material {
+            material.0 = decal.material.clone();
+        }
+    }
+}
 pub type DecalMaterialExtension = ExtendedMaterial<StandardMaterial, DecalMaterial>;
 
-//need to attach this alongside a Mesh3d()  on the decal entity ! 
-pub type DecalMeshMaterial3d = MeshMaterial3d< DecalMaterialExtension >;
-
-
+pub type DecalMeshMaterial3d = MeshMaterial3d<DecalMaterialExtension>;
 
 impl MaterialExtension for DecalMaterial {
     fn fragment_shader() -> ShaderRef {
@@ -72,7 +344,7 @@ impl MaterialExtension for DecalMaterial {
         _pipeline: &MaterialExtensionPipeline,
         descriptor: &mut RenderPipelineDescriptor,
         _layout: &MeshVertexBufferLayoutRef,
-        _key: MaterialExtensionKey<Self>,
+        key: MaterialExtensionKey<Self>,
     ) -> Result<(), SpecializedMeshPipelineError> {
         if let Some(label) = &mut descriptor.label {
             *label = format!("decal_{}", *label).into();
@@ -80,23 +352,329 @@ impl MaterialExtension for DecalMaterial {
         if let Some(  depth) = &mut descriptor.depth_stencil {
             depth.depth_compare = CompareFunction::Always;
         }
+        let Some(fragment) = &mut descriptor.fragment else {
+            return Ok(());
+        };
+        let DecalMaterialKey {
+            blend_mode,
+            emissive_glow,
+            unlit,
+        } = key.bind_group_data;
+        if cfg!(not(feature = "webgl2")) {
+            fragment.shader_defs.push("DECAL_INSTANCES".into());
+        }
+        if unlit {
+            fragment.shader_defs.push("DECAL_UNLIT".into());
+        }
+        let emissive_glow = emissive_glow && !unlit && blend_mode != DecalBlendMode::Multiply;
+        if emissive_glow {
+            fragment.shader_defs.push("DECAL_EMISSIVE_GLOW".into());
+        }
+        let blend = match blend_mode {
+            DecalBlendMode::Alpha if emissive_glow => BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            DecalBlendMode::Alpha => BlendState::ALPHA_BLENDING,
+            DecalBlendMode::Premultiplied => {
+                fragment
+                    .shader_defs
+                    .push("DECAL_BLEND_PREMULTIPLIED".into());
+                BlendState::PREMULTIPLIED_ALPHA_BLENDING
+            }
+            DecalBlendMode::Additive => {
+                fragment.shader_defs.push("DECAL_BLEND_ADDITIVE".into());
+                BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: KEEP_DESTINATION_ALPHA,
+                }
+            }
+            DecalBlendMode::Multiply => {
+                fragment.shader_defs.push("DECAL_BLEND_MULTIPLY".into());
+                BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::Dst,
+                        dst_factor: BlendFactor::Zero,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: KEEP_DESTINATION_ALPHA,
+                }
+            }
+        };
+        for target in fragment.targets.iter_mut().flatten() {
+            target.blend = Some(blend);
+        }
 
         Ok(())
     }
 }
 
+const KEEP_DESTINATION_ALPHA: BlendComponent = BlendComponent {
+    src_factor: BlendFactor::Zero,
+    dst_factor: BlendFactor::One,
+    operation: BlendOperation::Add,
+};
+
 /// This is the struct that will be passed to your shader
 #[derive(Asset, AsBindGroup, TypePath, Debug, Clone)]
+#[uniform(200, DecalMaterialUniform)]
+#[bind_group_data(DecalMaterialKey)]
 pub struct DecalMaterial {
-    #[uniform(200)]
-    /// Variable for how far the decal will fade onto intersecting geometry.
-    /// Default is 8.0
-    pub depth_fade_factor: f32,
+    /// Variable for how far the decal will fade onto intersecting geometry, the inverse of the
+    /// distance in world units from the decal plane at which it is gone. See
+    /// [`DecalMaterial::with_fade_distance`].
+    /// Default is `None`, which uses [`DecalSettings::default_fade_factor`].
+    pub depth_fade_factor: Option<f32>,
+    /// Replaces `depth_fade_factor` for surfaces behind the decal plane, so a decal can hug bumps
+    /// but not bleed into pits or the other way around. See
+    /// [`DecalMaterial::with_fade_distances`].
+    pub back_fade_factor: Option<f32>,
+    /// Shape of the fade onto intersecting geometry.
+    pub fade_curve: DecalFadeCurve,
+    /// Never paints surfaces further than this from the decal plane, in world units, whatever the
+    /// fade curve leaves of them.
+    pub max_projection_distance: Option<f32>,
+    /// Never paints surfaces further than this from the decal in view depth, in world units. Since
+    /// the depth test is disabled this keeps decals from showing on occluders in front of them or
+    /// geometry far behind them at grazing angles.
+    pub max_view_distance: Option<f32>,
+    /// Part of the base color texture shown by the decal, so many decals can share one texture.
+    pub atlas: DecalAtlas,
+    /// Fades the decal out when seen at grazing angles, where the contact deformation smears it.
+    /// Start and end angles in radians between the view direction and the decal's normal, the
+    /// decal is fully visible below the start angle and gone past the end angle. The smaller of
+    /// the two is used as the start, equal angles cut the decal off sharply.
+    pub angle_fade: Option<(f32, f32)>,
+    /// Fades the decal out towards the border of its deformed uvs, so textures with content up to
+    /// their edges don't streak where the deformation pushes uvs past them.
+    pub edge_mask: DecalEdgeMask,
+    /// How the decal is blended onto the surface, this replaces the blending of the base
+    /// material's `alpha_mode`, which should stay [`AlphaMode::Blend`].
+    pub blend_mode: DecalBlendMode,
+    /// Adds the base material's `emissive` and `emissive_texture` on top of the decal instead of
+    /// blending them with its base color, so they glow where the base color is transparent and
+    /// only fade with the contact fade and opacity. Use an HDR camera to feed bloom. Ignored by
+    /// [`DecalBlendMode::Multiply`] and unlit decals.
+    pub emissive_glow: bool,
+    /// Skips lighting and only shows the base color texture and tint. Compiled into its own
+    /// shader variant that leaves out the receiver's normal, which makes it much cheaper for large
+    /// overlays. [`StandardMaterial::unlit`] has no effect on decals.
+    pub unlit: bool,
+    /// Per-entity decal data, leave this at its default. Left out with the `webgl2` feature.
+    #[cfg_attr(not(feature = "webgl2"), storage(201, read_only))]
+    pub instances: Handle<ShaderStorageBuffer>,
+    /// [`DecalReceiver`] groups under every pixel, leave this at its default.
+    #[texture(202, sample_type = "u_int")]
+    pub receiver_mask: Handle<Image>,
 }
 impl Default for DecalMaterial {
     fn default() -> Self {
         Self {
-            depth_fade_factor: 8.0,
+            depth_fade_factor: None,
+            back_fade_factor: None,
+            fade_curve: DecalFadeCurve::Linear,
+            max_projection_distance: None,
+            max_view_distance: None,
+            atlas: DecalAtlas::Full,
+            angle_fade: None,
+            edge_mask: DecalEdgeMask::None,
+            blend_mode: DecalBlendMode::Alpha,
+            emissive_glow: false,
+            unlit: false,
+            instances: DECAL_INSTANCES_HANDLE,
+            receiver_mask: DECAL_RECEIVER_MASK_HANDLE,
+        }
+    }
+}
+
+impl DecalMaterial {
+    /// Fades the decal out over `distance` world units into intersecting geometry.
+    pub fn with_fade_distance(mut self, distance: f32) -> Self {
+        self.depth_fade_factor = Some(1.0 / distance.max(f32::EPSILON));
+        self
+    }
+    /// Fades the decal out over `front` world units into geometry in front of the decal plane and
+    /// `back` world units into geometry behind it.
+    pub fn with_fade_distances(mut self, front: f32, back: f32) -> Self {
+        self.depth_fade_factor = Some(1.0 / front.max(f32::EPSILON));
+        self.back_fade_factor = Some(1.0 / back.max(f32::EPSILON));
+        self
+    }
+    pub fn with_fade_curve(mut self, curve: DecalFadeCurve) -> Self {
+        self.fade_curve = curve;
+        self
+    }
+    /// Distance in world units from the decal plane at which the decal is gone, `None` if it
+    /// comes from [`DecalSettings::default_fade_factor`].
+    pub fn fade_distance(&self) -> Option<f32> {
+        self.depth_fade_factor.map(|factor| 1.0 / factor)
+    }
+    /// Distance in world units behind the decal plane at which the decal is gone, `None` if it
+    /// comes from [`DecalSettings::default_fade_factor`].
+    pub fn back_fade_distance(&self) -> Option<f32> {
+        self.back_fade_factor
+            .or(self.depth_fade_factor)
+            .map(|factor| 1.0 / factor)
+    }
+}
+
+/// How a [`DecalMaterial`] fades out with the distance of the surface from the decal plane.
+#[derive(Reflect, Debug, Clone, Copy, Default, PartialEq)]
+pub enum DecalFadeCurve {
+    /// Evenly over the fade distance.
+    #[default]
+    Linear,
+    /// Slowly at both ends and quickly in between.
+    Smoothstep,
+    /// Quickly at first and slowly towards the end.
+    Exponential,
+    /// `1 - t^power`, powers above 1 hold on longer and below 1 fade quicker. Powers are kept
+    /// above 0.001, since `0^0` is undefined on the GPU.
+    Power(f32),
+}
+
+/// Pipeline key of a [`DecalMaterial`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecalMaterialKey {
+    blend_mode: DecalBlendMode,
+    emissive_glow: bool,
+    unlit: bool,
+}
+impl From<&DecalMaterial> for DecalMaterialKey {
+    fn from(material: &DecalMaterial) -> Self {
+        Self {
+            blend_mode: material.blend_mode,
+            emissive_glow: material.emissive_glow,
+            unlit: material.unlit,
         }
     }
 }
+
+/// How a [`DecalMaterial`] is blended onto the surface. Every mode fades out the same way with the
+/// contact fade and opacity, towards leaving the surface unchanged.
+#[derive(Reflect, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DecalBlendMode {
+    /// Regular alpha blending.
+    #[default]
+    Alpha,
+    /// Alpha blending of a base color that is already multiplied by its alpha.
+    Premultiplied,
+    /// Adds the color to the surface, scaled by its alpha. Good for glowing magic effects.
+    Additive,
+    /// Multiplies the surface by the color, blended towards white by its alpha. Good for dirt and
+    /// grime.
+    Multiply,
+}
+
+/// Shape a [`DecalMaterial`] fades out towards at the border of its uvs.
+#[derive(Reflect, Debug, Clone, Copy, Default, PartialEq)]
+pub enum DecalEdgeMask {
+    /// No fade, uvs pushed past the border repeat the border pixels.
+    #[default]
+    None,
+    /// Fades out over the last `softness` uv units towards each edge.
+    Rect { softness: f32 },
+    /// Fades out over the last `softness` uv units towards the ellipse touching the edges.
+    Ellipse { softness: f32 },
+}
+
+/// Region of a texture atlas shown by a [`DecalMaterial`].
+///
+/// It is applied after the contact deformation, which is clamped to the region so neighbouring
+/// cells never bleed in.
+#[derive(Reflect, Debug, Clone, Copy, Default, PartialEq)]
+pub enum DecalAtlas {
+    /// The whole texture.
+    #[default]
+    Full,
+    /// Cell `index` of a grid of `columns` by `rows` equally sized cells, counted row by row from
+    /// the top left.
+    Grid { columns: u32, rows: u32, index: u32 },
+    /// An explicit region in uv coordinates.
+    Rect(Rect),
+}
+impl DecalAtlas {
+    /// The region in uv coordinates.
+    pub fn uv_rect(&self) -> Rect {
+        match *self {
+            DecalAtlas::Full => Rect::new(0.0, 0.0, 1.0, 1.0),
+            DecalAtlas::Grid {
+                columns,
+                rows,
+                index,
+            } => {
+                let grid = UVec2::new(columns, rows).max(UVec2::ONE);
+                let index = index % (grid.x * grid.y);
+                let cell = Vec2::ONE / grid.as_vec2();
+                let min = UVec2::new(index % grid.x, index / grid.x).as_vec2() * cell;
+                Rect::from_corners(min, min + cell)
+            }
+            DecalAtlas::Rect(rect) => rect,
+        }
+    }
+}
+
+impl From<&DecalMaterial> for DecalMaterialUniform {
+    fn from(material: &DecalMaterial) -> Self {
+        let atlas = material.atlas.uv_rect();
+        let atlas_grid = match material.atlas {
+            DecalAtlas::Grid { columns, rows, .. } => UVec2::new(columns, rows).max(UVec2::ONE),
+            _ => UVec2::ZERO,
+        };
+        let (edge_mask, edge_softness) = match material.edge_mask {
+            DecalEdgeMask::None => (0, 0.0),
+            DecalEdgeMask::Rect { softness } => (1, softness.max(1e-4)),
+            DecalEdgeMask::Ellipse { softness } => (2, softness.max(1e-4)),
+        };
+        let (fade_curve, fade_power) = match material.fade_curve {
+            DecalFadeCurve::Linear => (0, 1.0),
+            DecalFadeCurve::Smoothstep => (1, 1.0),
+            DecalFadeCurve::Exponential => (2, 1.0),
+            DecalFadeCurve::Power(power) => (3, power.max(1e-3)),
+        };
+        Self {
+            // Decals with a `Decal` get the project default through their instance data.
+            depth_fade_factor: material.depth_fade_factor.unwrap_or(DEFAULT_FADE_FACTOR),
+            atlas_rect: atlas.min.extend(atlas.width()).extend(atlas.height()),
+            atlas_grid,
+            // Below any cosine the shader sees, so nothing fades.
+            angle_fade: material
+                .angle_fade
+                .map_or(Vec2::new(-1.0, -2.0), |(start, end)| {
+                    // The shader's `smoothstep` needs the start cosine strictly above the end one.
+                    let (start, end) = (start.min(end), start.max(end));
+                    Vec2::new(start.cos(), end.max(start + 1e-4).cos())
+                }),
+            edge_mask,
+            edge_softness,
+            fade_curve,
+            fade_power,
+            back_fade_factor: material.back_fade_factor.unwrap_or(-1.0),
+            max_plane_distance: material.max_projection_distance.unwrap_or(f32::MAX),
+            max_view_distance: material.max_view_distance.unwrap_or(f32::MAX),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn angle_fade(start: f32, end: f32) -> Vec2 {
+        DecalMaterialUniform::from(&DecalMaterial {
+            angle_fade: Some((start, end)),
+            ..default()
+        })
+        .angle_fade
+    }
+
+    #[test]
+    fn angle_fade_cosines_are_ordered() {
+        assert_eq!(angle_fade(1.2, 0.8), angle_fade(0.8, 1.2));
+        let fade = angle_fade(0.8, 1.2);
+        assert!(fade.x > fade.y);
+        let fade = angle_fade(1.0, 1.0);
+        assert!(fade.x > fade.y);
+    }
+}
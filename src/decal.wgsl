@@ -5,50 +5,116 @@
     pbr_fragment::pbr_input_from_standard_material,
     pbr_functions::alpha_discard,
     prepass_utils::prepass_depth,
-    view_transformations::{depth_ndc_to_view_z},
+    view_transformations::{depth_ndc_to_view_z, frag_coord_to_ndc, position_ndc_to_world},
     mesh_view_bindings as view_bindings,
     parallax_mapping::parallaxed_uv,
+    mesh_functions::get_tag,
+    pbr_deferred_types::unpack_24bit_normal,
+    pbr_bindings,
+    pbr_types::{pbr_input_new, STANDARD_MATERIAL_FLAGS_BASE_COLOR_TEXTURE_BIT},
+    utils::octahedral_decode,
 }
 
-#ifdef PREPASS_PIPELINE
-#import bevy_pbr::{
-    prepass_io::{VertexOutput, FragmentOutput},
-    pbr_deferred_functions::deferred_output,
-}
-#else
+// Decals are always drawn forward in the transparent pass, in deferred cameras that is after the
+// deferred lighting pass so they blend over the lit G-buffer.
 #import bevy_pbr::{
     forward_io::{VertexOutput, FragmentOutput},
     pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
 }
-#endif
 
 fn project_onto(lhs: vec3<f32>, rhs: vec3<f32>) -> vec3<f32> {
     let other_len_sq_rcp = 1. / dot(rhs, rhs);
     return rhs * dot(lhs, rhs) * other_len_sq_rcp;
 }
 
-struct CustomMaterial {
+struct DecalMaterial {
     depth_fade_factor: f32,
+    // Atlas region shown by the decal, min in xy and size in zw.
+    atlas_rect: vec4<f32>,
+    // Columns and rows of a grid atlas, zero otherwise.
+    atlas_grid: vec2<u32>,
+    // Cosines of the view angles where the grazing fade starts and ends.
+    angle_fade: vec2<f32>,
+    // 0 for no edge mask, 1 for a rectangular and 2 for an elliptical one.
+    edge_mask: u32,
+    // Width of the edge mask's falloff in uv units.
+    edge_softness: f32,
+    // 0 linear, 1 smoothstep, 2 exponential and 3 power depth fade.
+    fade_curve: u32,
+    fade_power: f32,
+    // Fade factor behind the decal plane, negative to use `depth_fade_factor`.
+    back_fade_factor: f32,
+    // Furthest a painted surface may be from the decal plane and from the decal in view depth.
+    max_plane_distance: f32,
+    max_view_distance: f32,
+}
+
+// Per-entity data, indexed by the mesh tag.
+struct DecalInstance {
+    // Overrides the material's atlas region unless its size is zero.
+    atlas_rect: vec4<f32>,
+    // Linear color multiplied onto the base color.
+    tint: vec4<f32>,
+    opacity: f32,
+    // Overrides the material's fade factor unless negative.
+    depth_fade_factor: f32,
+    // Cell of the material's atlas grid to show, unless all bits are set.
+    atlas_index: u32,
+    // Receiver groups the decal projects onto.
+    receivers: u32,
 }
 
 struct DecalInformation {
     deformed_uvs: vec2<f32>,
     world_position: vec4<f32>,
-    depth_alpha: f32
+    depth_alpha: f32,
+    // Frame of the surface under the decal, normal maps are applied relative to it.
+    receiver_normal: vec3<f32>,
+    receiver_tangent: vec4<f32>,
+
+}
 
+// Opacity left `t` of the way through the depth fade.
+fn depth_fade(t: f32, material: DecalMaterial) -> f32 {
+    let x = clamp(t, 0.0, 1.0);
+    switch material.fade_curve {
+        case 1u: {
+            return 1.0 - smoothstep(0.0, 1.0, x);
+        }
+        case 2u: {
+            return (exp(-4.0 * x) - exp(-4.0)) / (1.0 - exp(-4.0));
+        }
+        case 3u: {
+            return 1.0 - pow(x, material.fade_power);
+        }
+        default: {
+            return 1.0 - x;
+        }
+    }
 }
 
-fn decalize(in: VertexOutput, is_front: bool, depth_fade_factor: f32) -> DecalInformation {
+fn decalize(in: VertexOutput, is_front: bool, material: DecalMaterial) -> DecalInformation {
 
     let v_ray = view.world_position - in.world_position.xyz;
-    let model = bevy_pbr::mesh_functions::get_world_from_local(in.instance_index);
-    let scale = (model * vec4(1.0, 1.0, 1.0, 0.0)).xyz;
 
     // view vector
     let V = normalize(v_ray);
     let N = in.world_normal;
-    let T = in.world_tangent.xyz / scale;
-    let B = in.world_tangent.w * cross(N, T);
+    // Scale the tangent frame by how fast the uvs change per world unit across the quad, so the
+    // deformation follows both the mesh size and the entity's scale.
+    let p_dx = dpdx(in.world_position.xyz);
+    let p_dy = dpdy(in.world_position.xyz);
+    let uv_dx = dpdx(in.uv);
+    let uv_dy = dpdy(in.uv);
+    let p_dy_perp = cross(p_dy, N);
+    let p_dx_perp = cross(N, p_dx);
+    let inv_det = 1.0 / dot(p_dx, p_dy_perp);
+    let grad_u = (p_dy_perp * uv_dx.x + p_dx_perp * uv_dy.x) * inv_det;
+    let grad_v = (p_dy_perp * uv_dx.y + p_dx_perp * uv_dy.y) * inv_det;
+    let tangent = normalize(in.world_tangent.xyz);
+    let bitangent = in.world_tangent.w * cross(N, tangent);
+    let T = tangent * abs(dot(grad_u, tangent));
+    let B = bitangent * abs(dot(grad_v, bitangent));
     // Transform V from fragment to camera in world space to tangent space.
     let Vt = vec3(dot(V, T), dot(V, B), dot(V, N));
 
@@ -56,8 +122,17 @@ fn decalize(in: VertexOutput, is_front: bool, depth_fade_factor: f32) -> DecalIn
     let depth_pass_depth = depth_ndc_to_view_z(prepass_depth(in.position, 0u));
 
     let diff_depth = frag_depth - depth_pass_depth;
-    let diff_depth_abs = abs(diff_depth);
 
+    // Reconstruct the surface under the decal from the depth prepass.
+    let receiver_position = position_ndc_to_world(
+        vec3(frag_coord_to_ndc(in.position).xy, prepass_depth(in.position, 0u))
+    );
+    // Signed world space distance of the receiver from the decal plane, positive in front of it.
+    let plane_distance = dot(receiver_position - in.world_position.xyz, normalize(N));
+    if abs(plane_distance) > material.max_plane_distance
+        || abs(diff_depth) > material.max_view_distance {
+        discard;
+    }
 
     let contact_on_decal = project_onto(V * diff_depth, in.world_normal);
     let normal_depth = length(contact_on_decal);
@@ -73,30 +148,165 @@ fn decalize(in: VertexOutput, is_front: bool, depth_fade_factor: f32) -> DecalIn
         Vt,
         material_bind_group_slot
     );
+    // Fade out towards the border of the deformed uvs before they are clamped.
+    var edge = 1.0;
+    if material.edge_mask == 1u {
+        let inside = min(uv, vec2(1.0) - uv);
+        edge = smoothstep(0.0, material.edge_softness, min(inside.x, inside.y));
+    } else if material.edge_mask == 2u {
+        let inside = 0.5 - length(uv - vec2(0.5));
+        edge = smoothstep(0.0, material.edge_softness, inside);
+    }
+
+    // Keep the deformed uvs inside the atlas region so neighbouring cells don't bleed in.
+    uv = material.atlas_rect.xy + clamp(uv, vec2(0.0), vec2(1.0)) * material.atlas_rect.zw;
+
+    var fade_factor = material.depth_fade_factor;
+    if plane_distance < 0.0 && material.back_fade_factor >= 0.0 {
+        fade_factor = material.back_fade_factor;
+    }
+    var alpha = depth_fade(abs(plane_distance) * fade_factor, material) * edge;
+    // Fade out at grazing view angles, where the deformation smears the texture.
+    let view_cos = abs(Vt.z) / length(N);
+    alpha *= smoothstep(material.angle_fade.y, material.angle_fade.x, view_cos);
+
+#ifdef DECAL_UNLIT
+    // Unlit decals are never shaded, keep the quad's frame.
+    let receiver_normal = N;
+    let receiver_tangent = tangent;
+#else
+    // Its normal comes from the normal prepass or the G-buffer when the camera has one and from
+    // the depth derivatives otherwise. The decal's tangent is flattened onto it.
+    var receiver_normal = normalize(cross(dpdy(receiver_position), dpdx(receiver_position)));
+    if dot(receiver_normal, V) < 0.0 {
+        receiver_normal = -receiver_normal;
+    }
+#ifdef NORMAL_PREPASS
+    receiver_normal = bevy_pbr::prepass_utils::prepass_normal(in.position, 0u);
+#else ifdef DEFERRED_PREPASS
+    // Forward rendered meshes leave the G-buffer cleared, keep the reconstructed normal there.
+    let gbuffer = textureLoad(view_bindings::deferred_prepass_texture, vec2<i32>(in.position.xy), 0);
+    if any(gbuffer != vec4(0u)) {
+        receiver_normal = octahedral_decode(unpack_24bit_normal(gbuffer.a));
+    }
+#endif
+    var receiver_tangent = tangent - receiver_normal * dot(tangent, receiver_normal);
+    if dot(receiver_tangent, receiver_tangent) < 1e-6 {
+        receiver_tangent = cross(bitangent, receiver_normal);
+    }
+#endif
 
-    var alpha = clamp(1.0 - normal_depth * depth_fade_factor, 0.0, 1.0);
-    return DecalInformation(uv, vec4(in.world_position.xyz + V * diff_depth_abs, in.world_position.w), alpha);
+    return DecalInformation(
+        uv,
+        vec4(receiver_position, 1.0),
+        alpha,
+        receiver_normal,
+        vec4(normalize(receiver_tangent), in.world_tangent.w),
+    );
 }
 
 @group(2) @binding(200)
-var<uniform> custom_material: CustomMaterial;
+var<uniform> decal_material: DecalMaterial;
+#ifdef DECAL_INSTANCES
+@group(2) @binding(201)
+var<storage, read> decal_instances: array<DecalInstance>;
+#endif
+@group(2) @binding(202)
+var decal_receiver_mask: texture_2d<u32>;
 
 
 @fragment
 fn fragment(in: VertexOutput,
     @builtin(front_facing) is_front: bool) -> @location(0) vec4<f32> {
-    let decal_info = decalize(in, is_front, custom_material.depth_fade_factor);
+#ifdef DECAL_INSTANCES
+    let instance = decal_instances[get_tag(in.instance_index)];
+#else
+    // Without storage buffers every decal uses its material's values.
+    let instance = DecalInstance(vec4(0.0), vec4(1.0), 1.0, -1.0, 0xffffffffu, 0xffffffffu);
+#endif
+    // The mask covers the view, whatever its resolution.
+    let mask_size = textureDimensions(decal_receiver_mask);
+    let mask_uv = (in.position.xy - view.viewport.xy) / view.viewport.zw;
+    let mask_texel = min(vec2<u32>(mask_uv * vec2<f32>(mask_size)), mask_size - 1u);
+    if (textureLoad(decal_receiver_mask, mask_texel, 0).r & instance.receivers) == 0u {
+        discard;
+    }
+    var material = decal_material;
+    let grid = material.atlas_grid;
+    if instance.atlas_index != 0xffffffffu && grid.x > 0u {
+        let index = instance.atlas_index % (grid.x * grid.y);
+        let cell = 1.0 / vec2<f32>(grid);
+        let min = vec2<f32>(vec2(index % grid.x, index / grid.x)) * cell;
+        material.atlas_rect = vec4(min, cell);
+    }
+    if instance.atlas_rect.z > 0.0 {
+        material.atlas_rect = instance.atlas_rect;
+    }
+    if instance.depth_fade_factor >= 0.0 {
+        material.depth_fade_factor = instance.depth_fade_factor;
+    }
+    let decal_info = decalize(in, is_front, material);
+#ifdef DECAL_UNLIT
+    // Only the base color is shown, sample it directly instead of resolving the whole material.
+    var pbr_input = pbr_input_new();
+    pbr_input.material.flags = pbr_bindings::material.flags;
+    pbr_input.material.base_color = pbr_bindings::material.base_color;
+    pbr_input.frag_coord = in.position;
+    pbr_input.world_position = decal_info.world_position;
+    if (pbr_input.material.flags & STANDARD_MATERIAL_FLAGS_BASE_COLOR_TEXTURE_BIT) != 0u {
+        let uv = (pbr_bindings::material.uv_transform * vec3(decal_info.deformed_uvs, 1.0)).xy;
+        pbr_input.material.base_color *= textureSampleBias(
+            pbr_bindings::base_color_texture,
+            pbr_bindings::base_color_sampler,
+            uv,
+            view.mip_bias,
+        );
+    }
+#else
     var new_in = in;
     new_in.uv = decal_info.deformed_uvs;
+    // Shade the receiver rather than the flat quad, normal maps perturb the receiver's normal.
+    new_in.world_normal = decal_info.receiver_normal;
+    new_in.world_tangent = decal_info.receiver_tangent;
+    new_in.world_position = decal_info.world_position;
 
     var pbr_input = pbr_input_from_standard_material(new_in, is_front);
-    pbr_input.world_position = decal_info.world_position;
+#endif
+    pbr_input.material.base_color *= instance.tint;
+
+#ifdef DECAL_EMISSIVE_GLOW
+    // Lighting scales emissive by the base color's alpha, add it separately instead.
+    let emissive = pbr_input.material.emissive;
+    pbr_input.material.emissive = vec4(vec3(0.0), emissive.a);
+#endif
 
     var out: FragmentOutput;
+#ifdef DECAL_UNLIT
+    out.color = pbr_input.material.base_color;
+#else
     out.color = apply_pbr_lighting(pbr_input);
+#endif
     out.color = main_pass_post_lighting_processing(pbr_input, out.color);
 
-    var alpha = min(decal_info.depth_alpha, out.color.a);
+    var alpha = min(decal_info.depth_alpha, out.color.a) * instance.opacity;
 
-    return vec4(out.color.rgb, alpha);
+    // Every blend mode fades towards leaving the surface unchanged.
+#ifdef DECAL_BLEND_MULTIPLY
+    var color = vec4(mix(vec3(1.0), out.color.rgb, alpha), 1.0);
+#else ifdef DECAL_BLEND_ADDITIVE
+    var color = vec4(out.color.rgb * alpha, 0.0);
+#else ifdef DECAL_BLEND_PREMULTIPLIED
+    var color = out.color * decal_info.depth_alpha * instance.opacity;
+#else ifdef DECAL_EMISSIVE_GLOW
+    var color = vec4(out.color.rgb * alpha, alpha);
+#else
+    var color = vec4(out.color.rgb, alpha);
+#endif
+
+#ifdef DECAL_EMISSIVE_GLOW
+    // Blended premultiplied, so the glow only fades with contact and opacity.
+    let glow = emissive.rgb * mix(1.0, view.exposure, emissive.a) * decal_info.depth_alpha * instance.opacity;
+    color += vec4(glow, 0.0);
+#endif
+    return color;
 }
@@ -0,0 +1,22 @@
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings::view,
+    prepass_utils::prepass_depth,
+}
+
+// Writes the mesh's receiver groups wherever it is the surface left in the depth prepass.
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) u32 {
+    // The view is stretched over the whole mask, find the pixel of the depth prepass under it.
+    let mask_size = vec2(f32(#{DECAL_RECEIVER_MASK_WIDTH}u), f32(#{DECAL_RECEIVER_MASK_HEIGHT}u));
+    let scale = view.viewport.zw / mask_size;
+    let pixel = view.viewport.xy + in.position.xy * scale;
+    let depth = prepass_depth(vec4(pixel, 0.0, 0.0), 0u);
+    // The depth slope covers both the offset to that pixel and differences between this pipeline's
+    // and the prepass' vertex outputs, the floor keeps flat distant receivers from dropping out.
+    let tolerance = fwidth(in.position.z) * max(1.0, 1.0 / min(scale.x, scale.y)) + 1e-7;
+    if abs(in.position.z - depth) > tolerance {
+        discard;
+    }
+    return #{DECAL_RECEIVER_GROUPS}u;
+}
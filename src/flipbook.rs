@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+
+use crate::DecalAtlas;
+
+/// How a [`DecalFlipbook`] continues after its last frame.
+#[derive(Reflect, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlipbookMode {
+    /// Starts over from the first frame.
+    #[default]
+    Loop,
+    /// Stops on the last frame.
+    Once,
+    /// Plays backwards to the first frame, then forwards again.
+    PingPong,
+}
+
+/// Animates a [`Decal`](crate::Decal) through the cells of a sprite sheet laid out as a grid,
+/// replacing its material's [`DecalAtlas`] for this entity only.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct DecalFlipbook {
+    pub columns: u32,
+    pub rows: u32,
+    /// Number of frames, counted row by row from the top left. Defaults to every cell of the grid.
+    pub frame_count: u32,
+    pub fps: f32,
+    pub mode: FlipbookMode,
+    /// Seconds into the animation it starts at, so decals spawned together don't play in lockstep.
+    pub start_offset: f32,
+    /// Despawns the entity once a [`FlipbookMode::Once`] animation has shown its last frame.
+    pub despawn_on_finish: bool,
+    elapsed: f32,
+}
+impl DecalFlipbook {
+    pub fn new(columns: u32, rows: u32, fps: f32) -> Self {
+        Self {
+            columns,
+            rows,
+            frame_count: columns * rows,
+            fps,
+            mode: FlipbookMode::Loop,
+            start_offset: 0.0,
+            despawn_on_finish: false,
+            elapsed: 0.0,
+        }
+    }
+    pub fn with_frame_count(mut self, frame_count: u32) -> Self {
+        self.frame_count = frame_count;
+        self
+    }
+    pub fn with_mode(mut self, mode: FlipbookMode) -> Self {
+        self.mode = mode;
+        self
+    }
+    pub fn with_start_offset(mut self, start_offset: f32) -> Self {
+        self.start_offset = start_offset;
+        self
+    }
+    /// Plays once and despawns the entity afterwards.
+    pub fn despawning(mut self) -> Self {
+        self.mode = FlipbookMode::Once;
+        self.despawn_on_finish = true;
+        self
+    }
+
+    /// Frames advanced since the start, including the start offset.
+    fn progress(&self) -> u32 {
+        ((self.elapsed + self.start_offset) * self.fps).max(0.0) as u32
+    }
+    /// The frame currently shown.
+    pub fn frame(&self) -> u32 {
+        let frame_count = self.frame_count.max(1);
+        let progress = self.progress();
+        match self.mode {
+            FlipbookMode::Loop => progress % frame_count,
+            FlipbookMode::Once => progress.min(frame_count - 1),
+            FlipbookMode::PingPong => {
+                let period = (2 * frame_count - 2).max(1);
+                let frame = progress % period;
+                if frame < frame_count {
+                    frame
+                } else {
+                    period - frame
+                }
+            }
+        }
+    }
+    /// Whether a [`FlipbookMode::Once`] animation has played through its last frame.
+    pub fn finished(&self) -> bool {
+        self.mode == FlipbookMode::Once && self.progress() >= self.frame_count.max(1)
+    }
+    /// Region of the current frame in uv coordinates.
+    pub fn uv_rect(&self) -> Rect {
+        DecalAtlas::Grid {
+            columns: self.columns,
+            rows: self.rows,
+            index: self.frame(),
+        }
+        .uv_rect()
+    }
+}
+
+pub(crate) fn advance_decal_flipbooks(
+    mut commands: Commands,
+    mut flipbooks: Query<(Entity, &mut DecalFlipbook)>,
+    time: Res<Time>,
+) {
+    for (entity, mut flipbook) in &mut flipbooks {
+        flipbook.elapsed += time.delta_secs();
+        if flipbook.despawn_on_finish && flipbook.finished() {
+            commands.entity(entity).try_despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames shown at the start of each of the first `count` frame intervals.
+    fn frames(flipbook: DecalFlipbook, count: u32) -> Vec<u32> {
+        (0..count)
+            .map(|step| {
+                flipbook
+                    .clone()
+                    .with_start_offset((step as f32 + 0.5) / flipbook.fps)
+                    .frame()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_starts_over() {
+        let flipbook = DecalFlipbook::new(2, 2, 10.0);
+        assert_eq!(frames(flipbook, 6), [0, 1, 2, 3, 0, 1]);
+    }
+
+    #[test]
+    fn once_holds_the_last_frame_and_finishes() {
+        let flipbook = DecalFlipbook::new(3, 1, 10.0).with_mode(FlipbookMode::Once);
+        assert_eq!(frames(flipbook.clone(), 5), [0, 1, 2, 2, 2]);
+        assert!(!flipbook.clone().with_start_offset(0.25).finished());
+        assert!(flipbook.with_start_offset(0.35).finished());
+    }
+
+    #[test]
+    fn ping_pong_doesnt_repeat_the_ends() {
+        let flipbook = DecalFlipbook::new(4, 1, 10.0).with_mode(FlipbookMode::PingPong);
+        assert_eq!(frames(flipbook, 9), [0, 1, 2, 3, 2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn frame_count_limits_the_grid() {
+        let flipbook = DecalFlipbook::new(4, 4, 10.0).with_frame_count(3);
+        assert_eq!(frames(flipbook, 4), [0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn single_frame_never_moves() {
+        for mode in [
+            FlipbookMode::Loop,
+            FlipbookMode::Once,
+            FlipbookMode::PingPong,
+        ] {
+            let flipbook = DecalFlipbook::new(1, 1, 10.0).with_mode(mode);
+            assert_eq!(frames(flipbook, 3), [0, 0, 0]);
+        }
+        let once = DecalFlipbook::new(1, 1, 10.0).with_mode(FlipbookMode::Once);
+        assert!(!once.clone().finished());
+        assert!(once.with_start_offset(0.15).finished());
+        // A frame count of zero behaves like a single frame.
+        let empty = DecalFlipbook::new(2, 2, 10.0).with_frame_count(0);
+        assert_eq!(frames(empty, 2), [0, 0]);
+    }
+
+    #[test]
+    fn uv_rect_follows_the_frame() {
+        let flipbook = DecalFlipbook::new(2, 2, 10.0).with_start_offset(0.25);
+        assert_eq!(flipbook.uv_rect(), Rect::new(0.0, 0.5, 0.5, 1.0));
+    }
+}
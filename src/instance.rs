@@ -0,0 +1,284 @@
+use std::ops::Range;
+
+use bevy::{
+    asset::weak_handle,
+    platform::collections::HashMap,
+    prelude::*,
+    render::{mesh::MeshTag, storage::ShaderStorageBuffer},
+};
+#[cfg(not(feature = "webgl2"))]
+use bevy::{
+    asset::RenderAssetUsages,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{encase, BufferUsages, ShaderType},
+        renderer::RenderQueue,
+        storage::GpuShaderStorageBuffer,
+        Extract,
+    },
+};
+
+use crate::{
+    shader_types::DecalInstance, Decal, DecalAnimation, DecalFlipbook, DecalLifetime,
+    DecalMaterialExtension, DecalMeshMaterial3d, DecalSettings,
+};
+
+/// Storage buffer with one [`DecalInstance`] per decal, shared by every decal material. It is
+/// allocated once with [`DecalPlugin::instance_capacity`](crate::DecalPlugin::instance_capacity)
+/// slots and written in place from then on.
+pub const DECAL_INSTANCES_HANDLE: Handle<ShaderStorageBuffer> =
+    weak_handle!("290f0a8e-0661-40cd-bb4a-b332471b02fa");
+
+/// Per-entity overrides for a [`Decal`], so variations don't need their own material asset and
+/// thousands of decals can share one.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component, Default)]
+pub struct DecalInstanceParams {
+    /// Multiplied onto the material's base color.
+    pub tint: Color,
+    /// Multiplied onto the decal's alpha.
+    pub opacity: f32,
+    /// Overrides [`DecalMaterial::depth_fade_factor`](crate::DecalMaterial::depth_fade_factor)
+    /// and [`DecalSettings::default_fade_factor`].
+    pub fade_factor: Option<f32>,
+    /// Cell of the material's [`DecalAtlas::Grid`](crate::DecalAtlas::Grid) to show instead of
+    /// its own index, ignored for other atlas kinds.
+    pub atlas_index: Option<u32>,
+    /// [`DecalReceiver`](crate::DecalReceiver) groups the decal projects onto, all of them by
+    /// default.
+    pub receivers: u32,
+}
+impl Default for DecalInstanceParams {
+    fn default() -> Self {
+        Self {
+            tint: Color::WHITE,
+            opacity: 1.0,
+            fade_factor: None,
+            atlas_index: None,
+            receivers: u32::MAX,
+        }
+    }
+}
+
+/// Slots in the [`DECAL_INSTANCES_HANDLE`] buffer. Every [`Decal`] gets one and points its
+/// [`MeshTag`] at it, slot 0 is left at its default for decals spawned without [`Decal`] and
+/// decals past the capacity.
+#[derive(Resource)]
+pub(crate) struct DecalInstances {
+    capacity: u32,
+    data: Vec<DecalInstance>,
+    free: Vec<u32>,
+    slots: HashMap<Entity, u32>,
+    /// Slots changed this frame, extracted to the render world.
+    changed: Option<Range<u32>>,
+    warned: bool,
+}
+impl DecalInstances {
+    pub(crate) fn new(capacity: u32) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            data: vec![DecalInstance::default()],
+            free: Vec::new(),
+            slots: HashMap::default(),
+            changed: None,
+            warned: false,
+        }
+    }
+    /// The buffer backing every slot, filled with defaults.
+    #[cfg(not(feature = "webgl2"))]
+    pub(crate) fn buffer(&self) -> ShaderStorageBuffer {
+        let mut buffer =
+            ShaderStorageBuffer::from(vec![DecalInstance::default(); self.capacity as usize]);
+        buffer.buffer_description.usage |= BufferUsages::COPY_DST;
+        buffer.asset_usage = RenderAssetUsages::RENDER_WORLD;
+        buffer
+    }
+    fn allocate(&mut self, entity: Entity) -> Option<u32> {
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None if self.data.len() < self.capacity as usize => {
+                self.data.push(DecalInstance::default());
+                self.data.len() as u32 - 1
+            }
+            None => return None,
+        };
+        self.slots.insert(entity, slot);
+        Some(slot)
+    }
+    fn release(&mut self, entity: Entity) {
+        if let Some(slot) = self.slots.remove(&entity) {
+            self.set(slot, DecalInstance::default());
+            self.free.push(slot);
+        }
+    }
+    /// Replaces the data in `slot`, only uploading it if something actually changed.
+    fn set(&mut self, slot: u32, instance: DecalInstance) {
+        if slot == 0 {
+            return;
+        }
+        if let Some(current) = self.data.get_mut(slot as usize)
+            && *current != instance
+        {
+            *current = instance;
+            self.changed = Some(match self.changed.take() {
+                Some(changed) => changed.start.min(slot)..changed.end.max(slot + 1),
+                None => slot..slot + 1,
+            });
+        }
+    }
+}
+
+pub(crate) fn assign_decal_instances(
+    mut added: Query<(Entity, &mut MeshTag), Added<Decal>>,
+    mut removed: RemovedComponents<Decal>,
+    mut instances: ResMut<DecalInstances>,
+) {
+    // Last frame's changes have been extracted by now.
+    instances.changed = None;
+    for entity in removed.read() {
+        instances.release(entity);
+    }
+    for (entity, mut tag) in &mut added {
+        tag.0 = instances.allocate(entity).unwrap_or_else(|| {
+            if !instances.warned {
+                instances.warned = true;
+                warn!(
+                    "More than {} decals exist, the rest ignore their per-entity data. Raise \
+                     DecalPlugin::instance_capacity to fit them.",
+                    instances.capacity - 1
+                );
+            }
+            0
+        });
+    }
+}
+
+/// Gathers the per-entity data of every decal from the components driving it.
+#[allow(clippy::type_complexity)]
+pub(crate) fn write_decal_instances(
+    decals: Query<
+        (
+            &MeshTag,
+            &DecalMeshMaterial3d,
+            Option<&DecalInstanceParams>,
+            Option<&DecalFlipbook>,
+            Option<&DecalLifetime>,
+            Option<&DecalAnimation>,
+        ),
+        With<Decal>,
+    >,
+    materials: Res<Assets<DecalMaterialExtension>>,
+    settings: Res<DecalSettings>,
+    mut instances: ResMut<DecalInstances>,
+) {
+    for (tag, material, params, flipbook, lifetime, animation) in &decals {
+        let mut instance = DecalInstance::default();
+        let material_fade = materials
+            .get(&material.0)
+            .and_then(|material| material.extension.depth_fade_factor);
+        if material_fade.is_none() {
+            instance.depth_fade_factor = settings.default_fade_factor;
+        }
+        if let Some(params) = params {
+            instance.tint = params.tint.to_linear().to_vec4();
+            instance.opacity = params.opacity;
+            if let Some(fade_factor) = params.fade_factor {
+                instance.depth_fade_factor = fade_factor;
+            }
+            instance.atlas_index = params.atlas_index.unwrap_or(u32::MAX);
+            instance.receivers = params.receivers;
+        }
+        if let Some(flipbook) = flipbook {
+            let rect = flipbook.uv_rect();
+            instance.atlas_rect = rect.min.extend(rect.width()).extend(rect.height());
+        }
+        if let Some(lifetime) = lifetime {
+            instance.opacity *= lifetime.opacity();
+        }
+        if let Some(animation) = animation {
+            let age = animation.age(lifetime);
+            instance.opacity *= animation.opacity(age);
+            instance.tint *= animation.tint(age).to_vec4();
+        }
+        instances.set(tag.0, instance);
+    }
+}
+
+/// Instance data waiting to be written to the GPU buffer, as the first slot and the data from
+/// there on, in the render world.
+#[cfg(not(feature = "webgl2"))]
+#[derive(Resource, Default)]
+pub(crate) struct DecalInstanceWrites(Vec<(u32, Vec<DecalInstance>)>);
+
+#[cfg(not(feature = "webgl2"))]
+pub(crate) fn extract_decal_instances(
+    mut writes: ResMut<DecalInstanceWrites>,
+    instances: Extract<Res<DecalInstances>>,
+) {
+    if let Some(changed) = &instances.changed {
+        let data = instances.data[changed.start as usize..changed.end as usize].to_vec();
+        writes.0.push((changed.start, data));
+    }
+}
+
+/// Writes changed slots into the buffer in place, so the materials binding it never need to
+/// be prepared again.
+#[cfg(not(feature = "webgl2"))]
+pub(crate) fn write_decal_instance_buffer(
+    mut writes: ResMut<DecalInstanceWrites>,
+    buffers: Res<RenderAssets<GpuShaderStorageBuffer>>,
+    queue: Res<RenderQueue>,
+) {
+    // The buffer starts out with defaults, writes wait until it has been created.
+    let Some(buffer) = buffers.get(&DECAL_INSTANCES_HANDLE) else {
+        return;
+    };
+    for (start, data) in writes.0.drain(..) {
+        let mut bytes = encase::StorageBuffer::new(Vec::new());
+        bytes.write(&data).unwrap();
+        let offset = u64::from(start) * u64::from(DecalInstance::min_size());
+        queue.write_buffer(&buffer.buffer, offset, bytes.as_ref());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(opacity: f32) -> DecalInstance {
+        DecalInstance {
+            opacity,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn slots_stop_at_the_capacity_and_are_reused() {
+        let mut instances = DecalInstances::new(3);
+        let [a, b, c] = [1, 2, 3].map(Entity::from_raw);
+        assert_eq!(instances.allocate(a), Some(1));
+        assert_eq!(instances.allocate(b), Some(2));
+        assert_eq!(instances.allocate(c), None);
+        instances.release(a);
+        assert_eq!(instances.allocate(c), Some(1));
+    }
+
+    #[test]
+    fn only_changed_slots_are_written() {
+        let mut instances = DecalInstances::new(8);
+        for index in 1..6 {
+            instances.allocate(Entity::from_raw(index));
+        }
+        instances.set(2, instance(0.5));
+        instances.set(4, instance(0.25));
+        assert_eq!(instances.changed, Some(2..5));
+
+        instances.changed = None;
+        instances.set(4, instance(0.25));
+        assert_eq!(instances.changed, None);
+        // Slot 0 is shared by decals without a slot of their own and keeps its defaults.
+        instances.set(0, instance(0.5));
+        assert_eq!(instances.changed, None);
+        assert_eq!(instances.data[0], DecalInstance::default());
+    }
+}
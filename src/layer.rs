@@ -0,0 +1,155 @@
+use bevy::{
+    core_pipeline::core_3d::Transparent3d,
+    platform::collections::HashMap,
+    prelude::*,
+    render::{render_phase::ViewSortedRenderPhases, sync_world::MainEntity, Extract},
+};
+
+use crate::Decal;
+
+/// Draw order of overlapping decals. Decals in higher layers draw on top of lower ones no matter
+/// where the camera is, decals in the same layer draw in the order they were spawned.
+///
+/// Decals without one are in layer 0.
+#[derive(Component, Reflect, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[reflect(Component, Default)]
+pub struct DecalLayer(pub i32);
+
+/// When a decal was spawned, or placed again by the [`DecalPool`](crate::DecalPool).
+#[derive(Component, Debug, Clone, Copy)]
+pub(crate) struct DecalSequence(pub(crate) u64);
+
+pub(crate) fn sequence_decals(
+    mut commands: Commands,
+    decals: Query<Entity, (With<Decal>, Without<DecalSequence>)>,
+    mut next: Local<u64>,
+) {
+    for entity in &decals {
+        commands.entity(entity).insert(DecalSequence(*next));
+        *next += 1;
+    }
+}
+
+/// Layer and sequence of every decal, in the render world.
+#[derive(Resource, Default)]
+pub(crate) struct DecalDrawOrder(HashMap<MainEntity, (i32, u64)>);
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn extract_decal_draw_order(
+    mut order: ResMut<DecalDrawOrder>,
+    decals: Extract<Query<(Entity, Option<&DecalLayer>, &DecalSequence), With<Decal>>>,
+) {
+    order.0.clear();
+    for (entity, layer, sequence) in &decals {
+        order.0.insert(
+            entity.into(),
+            (layer.map_or(0, |layer| layer.0), sequence.0),
+        );
+    }
+}
+
+/// Reorders decals in the already distance sorted transparent phase by layer and sequence.
+///
+/// Decals only swap places with each other, so they still draw between the same other
+/// transparent items as before.
+pub(crate) fn sort_decals(
+    order: Res<DecalDrawOrder>,
+    mut phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
+) {
+    if order.0.is_empty() {
+        return;
+    }
+    for phase in phases.values_mut() {
+        sort_in_place(&mut phase.items, |item| {
+            order.0.get(&item.entity.1).copied()
+        });
+    }
+}
+
+/// Sorts the items with a key by it, keeping them in the slots items with a key were in and
+/// leaving the others where they are.
+fn sort_in_place<T, K: Ord>(items: &mut [T], key: impl Fn(&T) -> Option<K>) {
+    let (slots, keys): (Vec<usize>, Vec<K>) = items
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, item)| Some((slot, key(item)?)))
+        .unzip();
+    let mut sorted: Vec<usize> = (0..slots.len()).collect();
+    sorted.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+
+    // `at[i]` is the item currently in `slots[i]`, `location[d]` is where item `d` is now.
+    let mut at: Vec<usize> = (0..slots.len()).collect();
+    let mut location = at.clone();
+    for (target, &item) in sorted.iter().enumerate() {
+        let current = location[item];
+        if current == target {
+            continue;
+        }
+        items.swap(slots[current], slots[target]);
+        let displaced = at[target];
+        at[current] = displaced;
+        location[displaced] = current;
+        at[target] = item;
+        location[item] = target;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sorts `(key, name)` items by key, items without a key stay put.
+    fn sorted(items: &[(Option<u32>, char)]) -> String {
+        let mut items = items.to_vec();
+        sort_in_place(&mut items, |(key, _)| *key);
+        items.into_iter().map(|(_, name)| name).collect()
+    }
+
+    #[test]
+    fn items_without_a_key_keep_their_slot() {
+        let items = [
+            (Some(3), 'a'),
+            (None, 'x'),
+            (Some(1), 'b'),
+            (None, 'y'),
+            (Some(2), 'c'),
+        ];
+        assert_eq!(sorted(&items), "bxcya");
+    }
+
+    #[test]
+    fn every_permutation_ends_up_sorted() {
+        let names = ['a', 'b', 'c', 'd'];
+        for permutation in 0..24 {
+            // Decode the permutation from the factorial number system.
+            let mut left = names.to_vec();
+            let mut rest = permutation;
+            let mut items = Vec::new();
+            for radix in (1..=4).rev() {
+                let name = left.remove(rest % radix);
+                rest /= radix;
+                items.push((Some(name as u32), name));
+                items.push((None, '-'));
+            }
+            assert_eq!(sorted(&items), "a-b-c-d-");
+        }
+    }
+
+    #[test]
+    fn equal_keys_keep_their_order() {
+        let items = [
+            (Some(1), 'a'),
+            (Some(0), 'b'),
+            (Some(1), 'c'),
+            (Some(0), 'd'),
+        ];
+        assert_eq!(sorted(&items), "bdac");
+    }
+
+    #[test]
+    fn layers_come_before_sequence() {
+        let mut items = vec![(0, 5), (1, 0), (0, 2), (-1, 9)];
+        sort_in_place(&mut items, |&item| Some(item));
+        assert_eq!(items, [(-1, 9), (0, 2), (0, 5), (1, 0)]);
+    }
+}
@@ -5,32 +5,76 @@
 //! use bevy_contact_projective_decals::{DecalPlugin};
 //! fn main() {
 //!     App::new()
-//!         .add_plugins((DefaultPlugins, DecalPlugin))
+//!         .add_plugins((DefaultPlugins, DecalPlugin::default()))
 //!         .run();
 //! }
 //! ```
 //!
+//! Project-wide defaults are set on the plugin and end up in the [`DecalSettings`] resource:
+//! ```rs
+//! DecalPlugin {
+//!     default_fade_factor: 4.0,
+//!     max_decals: Some(1000),
+//!     ..default()
+//! }
+//! ```
+//!
 //! Spawning:
 //! ```rs
-//! commands.spawn(DecalBundle {
-//!     transform: Transform::from_xyz(x, y, z),
-//!     decal_material: decal_materials.add(ExtendedMaterial::<
-//!         StandardMaterial,
-//!         DecalMaterial,
-//!     > {
+//! commands.spawn((
+//!     Decal::new(decal_materials.add(DecalMaterialExtension {
 //!         base: StandardMaterial {
-//!             base_color_texture: Some(asset_server.load("my_decal.png"))),
+//!             base_color_texture: Some(asset_server.load("my_decal.png")),
 //!             base_color: color,
 //!             alpha_mode: AlphaMode::Blend,
 //!             ..default()
 //!         },
 //!         extension: DecalMaterial {
-//!             depth_fade_factor: 8.0,
+//!             depth_fade_factor: Some(8.0),
+//!             ..default()
 //!         },
-//!     }),
-//!     mesh: meshes.add(decal_mesh_quad(Vec2::splat(scale))),
-//!     ..default()
-//! });
+//!     }))
+//!     .with_size(Vec2::splat(scale)),
+//!     Transform::from_xyz(x, y, z),
+//! ));
+//! ```
+//!
+//! Or let the crate build and share the material and mesh for you:
+//! ```rs
+//! commands
+//!     .spawn_decal(asset_server.load("my_decal.png"))
+//!     .at(Vec3::new(x, y, z))
+//!     .facing(Vec3::Y)
+//!     .size(Vec2::splat(scale))
+//!     .tint(color);
 //! ```
+mod animation;
+mod commands;
 mod decal;
-pub use decal::{decal_mesh_quad, DecalMaterialExtension, DecalMeshMaterial3d, DecalMaterial, DecalPlugin};
+mod flipbook;
+mod instance;
+mod layer;
+mod lifetime;
+mod mesh;
+mod pool;
+mod prepass;
+mod receiver;
+mod settings;
+// `ShaderType` derives emit layout checks that recent compilers report as dead code.
+#[allow(dead_code)]
+mod shader_types;
+pub use animation::DecalAnimation;
+pub use commands::{DecalAppearance, DecalCommandsExt, DecalEntityCommandsExt};
+pub use decal::{
+    Decal, DecalAtlas, DecalBlendMode, DecalEdgeMask, DecalFadeCurve, DecalMaterial,
+    DecalMaterialExtension, DecalMaterialKey, DecalMeshMaterial3d, DecalPlugin,
+};
+pub use flipbook::{DecalFlipbook, FlipbookMode};
+pub use instance::{DecalInstanceParams, DECAL_INSTANCES_HANDLE};
+pub use layer::DecalLayer;
+pub use lifetime::{DecalExpired, DecalLifetime};
+pub use mesh::{decal_mesh_quad, DecalMeshCache, DecalMeshError, DecalQuadBuilder};
+pub use pool::DecalPool;
+pub use receiver::{DecalReceiver, NoDecals, DECAL_RECEIVER_MASK_HANDLE};
+pub use settings::DecalSettings;
+pub use shader_types::DecalMaterialUniform;
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+/// Despawns a [`Decal`](crate::Decal) after `duration` seconds, fading it out over the last
+/// `fade_out` seconds. Sends [`DecalExpired`] right before the despawn.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct DecalLifetime {
+    pub duration: f32,
+    pub fade_out: f32,
+    elapsed: f32,
+}
+impl DecalLifetime {
+    pub fn new(duration: f32, fade_out: f32) -> Self {
+        Self {
+            duration,
+            fade_out,
+            elapsed: 0.0,
+        }
+    }
+    /// Seconds left until the decal is despawned.
+    pub fn remaining(&self) -> f32 {
+        (self.duration - self.elapsed).max(0.0)
+    }
+    /// Fraction of the lifetime that has passed, from 0 to 1.
+    pub fn age(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        (self.elapsed / self.duration).clamp(0.0, 1.0)
+    }
+    pub fn finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+    /// Opacity multiplier, ramping from 1 to 0 over the last `fade_out` seconds.
+    pub fn opacity(&self) -> f32 {
+        if self.fade_out <= 0.0 {
+            return 1.0;
+        }
+        (self.remaining() / self.fade_out).clamp(0.0, 1.0)
+    }
+}
+
+/// Sent when a [`DecalLifetime`] runs out, the entity is despawned in the same frame.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DecalExpired {
+    /// Already despawned by the time the event is read.
+    pub entity: Entity,
+    /// Where the decal was when it expired.
+    pub transform: GlobalTransform,
+}
+
+pub(crate) fn tick_decal_lifetimes(
+    mut commands: Commands,
+    mut lifetimes: Query<(Entity, &mut DecalLifetime, Option<&GlobalTransform>)>,
+    mut expired: EventWriter<DecalExpired>,
+    time: Res<Time>,
+) {
+    for (entity, mut lifetime, transform) in &mut lifetimes {
+        lifetime.elapsed += time.delta_secs();
+        if lifetime.finished() {
+            expired.write(DecalExpired {
+                entity,
+                transform: transform.copied().unwrap_or_default(),
+            });
+            commands.entity(entity).try_despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lifetime(duration: f32, fade_out: f32, elapsed: f32) -> DecalLifetime {
+        DecalLifetime {
+            elapsed,
+            ..DecalLifetime::new(duration, fade_out)
+        }
+    }
+
+    #[test]
+    fn fades_out_over_the_last_seconds() {
+        assert_eq!(lifetime(4.0, 1.0, 0.0).opacity(), 1.0);
+        assert_eq!(lifetime(4.0, 1.0, 3.0).opacity(), 1.0);
+        assert_eq!(lifetime(4.0, 1.0, 3.5).opacity(), 0.5);
+        assert_eq!(lifetime(4.0, 1.0, 4.0).opacity(), 0.0);
+        assert_eq!(lifetime(4.0, 1.0, 5.0).opacity(), 0.0);
+        // Fades longer than the lifetime start partway through.
+        assert_eq!(lifetime(1.0, 2.0, 0.0).opacity(), 0.5);
+        assert_eq!(lifetime(4.0, 0.0, 3.9).opacity(), 1.0);
+    }
+
+    #[test]
+    fn age_runs_from_zero_to_one() {
+        assert_eq!(lifetime(4.0, 1.0, 0.0).age(), 0.0);
+        assert_eq!(lifetime(4.0, 1.0, 2.0).age(), 0.5);
+        assert_eq!(lifetime(4.0, 1.0, 6.0).age(), 1.0);
+        assert!(!lifetime(4.0, 1.0, 3.9).finished());
+        assert!(lifetime(4.0, 1.0, 4.0).finished());
+    }
+
+    #[test]
+    fn zero_durations_expire_immediately() {
+        for duration in [0.0, -1.0] {
+            let lifetime = lifetime(duration, 1.0, 0.0);
+            assert_eq!(lifetime.age(), 1.0);
+            assert_eq!(lifetime.remaining(), 0.0);
+            assert_eq!(lifetime.opacity(), 0.0);
+            assert!(lifetime.finished());
+        }
+    }
+}
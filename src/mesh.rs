@@ -0,0 +1,341 @@
+use std::{
+    error::Error,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use bevy::{
+    asset::RenderAssetUsages,
+    platform::collections::HashMap,
+    prelude::*,
+    render::mesh::{GenerateTangentsError, Indices, PrimitiveTopology},
+};
+
+/// A unit quad, rotated so that its front face points along `normal`, with generated tangents.
+///
+/// # Panics
+///
+/// Panics if `normal` is zero or not finite, use [`DecalQuadBuilder`] to handle that instead.
+pub fn decal_mesh_quad(normal: Vec3) -> Mesh {
+    DecalQuadBuilder::new(normal)
+        .build()
+        .expect("decal quad normal should be finite and non-zero")
+}
+
+/// Builds decal quads, see [`DecalQuadBuilder::build`].
+#[derive(Clone, Copy, Debug)]
+pub struct DecalQuadBuilder {
+    /// Direction the front face points, the decal projects away from it.
+    pub normal: Vec3,
+    /// Direction of the top edge of the texture, projected onto the quad's plane.
+    ///
+    /// Defaults to rotating [`Vec3::Y`] by the shortest arc from [`Vec3::Z`] to `normal`.
+    pub up: Option<Vec3>,
+    pub size: Vec2,
+    /// Point of the quad placed at the mesh origin, from `(-0.5, -0.5)` at the bottom left corner
+    /// to `(0.5, 0.5)` at the top right one.
+    pub pivot: Vec2,
+    /// Part of the texture mapped onto the quad, in uv coordinates.
+    pub uv_rect: Rect,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+impl Default for DecalQuadBuilder {
+    fn default() -> Self {
+        Self {
+            normal: Vec3::Y,
+            up: None,
+            size: Vec2::ONE,
+            pivot: Vec2::ZERO,
+            uv_rect: Rect::new(0.0, 0.0, 1.0, 1.0),
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+}
+impl DecalQuadBuilder {
+    pub fn new(normal: Vec3) -> Self {
+        Self {
+            normal,
+            ..default()
+        }
+    }
+    pub fn up(mut self, up: Vec3) -> Self {
+        self.up = Some(up);
+        self
+    }
+    pub fn size(mut self, size: Vec2) -> Self {
+        self.size = size;
+        self
+    }
+    pub fn pivot(mut self, pivot: Vec2) -> Self {
+        self.pivot = pivot;
+        self
+    }
+    pub fn uv_rect(mut self, uv_rect: Rect) -> Self {
+        self.uv_rect = uv_rect;
+        self
+    }
+    pub fn flip_x(mut self, flip_x: bool) -> Self {
+        self.flip_x = flip_x;
+        self
+    }
+    pub fn flip_y(mut self, flip_y: bool) -> Self {
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// Builds the quad with normals, uvs and generated tangents.
+    pub fn build(&self) -> Result<Mesh, DecalMeshError> {
+        let normal = self
+            .normal
+            .try_normalize()
+            .ok_or(DecalMeshError::InvalidNormal(self.normal))?;
+        let up = match self.up {
+            Some(up) => up
+                .reject_from_normalized(normal)
+                .try_normalize()
+                .ok_or(DecalMeshError::InvalidUp(up))?,
+            None => Quat::from_rotation_arc(Vec3::Z, normal) * Vec3::Y,
+        };
+        if !self.size.is_finite() || self.size.cmple(Vec2::ZERO).any() {
+            return Err(DecalMeshError::InvalidSize(self.size));
+        }
+        let right = up.cross(normal);
+
+        let half = self.size / 2.0;
+        let offset = self.pivot * self.size;
+        let positions = [
+            Vec2::new(half.x, half.y),
+            Vec2::new(-half.x, half.y),
+            Vec2::new(-half.x, -half.y),
+            Vec2::new(half.x, -half.y),
+        ]
+        .map(|corner| {
+            let corner = corner - offset;
+            (right * corner.x + up * corner.y).to_array()
+        });
+        let uvs = [[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]].map(|[u, v]| {
+            let u = if self.flip_x { 1.0 - u } else { u };
+            let v = if self.flip_y { 1.0 - v } else { v };
+            (self.uv_rect.min + Vec2::new(u, v) * self.uv_rect.size()).to_array()
+        });
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_indices(Indices::U32(vec![0, 1, 2, 0, 2, 3]))
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions.to_vec())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![normal.to_array(); 4])
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs.to_vec())
+        .with_generated_tangents()
+        .map_err(DecalMeshError::Tangents)
+    }
+
+    fn bits(&self) -> [u32; 17] {
+        let up = self.up.unwrap_or(Vec3::ZERO).to_array();
+        let mut bits = [0.0; 17];
+        bits[..3].copy_from_slice(&self.normal.to_array());
+        bits[3..6].copy_from_slice(&up);
+        bits[6..8].copy_from_slice(&self.size.to_array());
+        bits[8..10].copy_from_slice(&self.pivot.to_array());
+        bits[10..12].copy_from_slice(&self.uv_rect.min.to_array());
+        bits[12..14].copy_from_slice(&self.uv_rect.max.to_array());
+        bits[14] = self.flip_x as u8 as f32;
+        bits[15] = self.flip_y as u8 as f32;
+        bits[16] = self.up.is_some() as u8 as f32;
+        bits.map(f32::to_bits)
+    }
+}
+impl PartialEq for DecalQuadBuilder {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits() == other.bits()
+    }
+}
+impl Eq for DecalQuadBuilder {}
+impl Hash for DecalQuadBuilder {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits().hash(state);
+    }
+}
+
+/// Why [`DecalQuadBuilder::build`] failed.
+#[derive(Debug)]
+pub enum DecalMeshError {
+    /// The normal is zero or not finite.
+    InvalidNormal(Vec3),
+    /// The up vector is parallel to the normal, zero or not finite.
+    InvalidUp(Vec3),
+    /// A side of the quad is not a finite positive length.
+    InvalidSize(Vec2),
+    Tangents(GenerateTangentsError),
+}
+impl fmt::Display for DecalMeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidNormal(normal) => write!(f, "invalid decal normal {normal}"),
+            Self::InvalidUp(up) => write!(f, "invalid decal up direction {up}"),
+            Self::InvalidSize(size) => write!(f, "invalid decal size {size}"),
+            Self::Tangents(error) => write!(f, "failed to generate decal tangents: {error}"),
+        }
+    }
+}
+impl Error for DecalMeshError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Tangents(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Hands out one shared mesh per distinct [`DecalQuadBuilder`], so any number of decals only
+/// upload a handful of vertex buffers. [`Decal`](crate::Decal) gets its mesh from here.
+#[derive(Resource, Default)]
+pub struct DecalMeshCache {
+    meshes: HashMap<DecalQuadBuilder, Handle<Mesh>>,
+}
+impl DecalMeshCache {
+    /// Returns the mesh for `quad`, building and adding it to `meshes` the first time.
+    pub fn get_or_insert(
+        &mut self,
+        quad: DecalQuadBuilder,
+        meshes: &mut Assets<Mesh>,
+    ) -> Result<Handle<Mesh>, DecalMeshError> {
+        if let Some(mesh) = self.meshes.get(&quad) {
+            return Ok(mesh.clone());
+        }
+        let mesh = meshes.add(quad.build()?);
+        self.meshes.insert(quad, mesh.clone());
+        Ok(mesh)
+    }
+    /// Number of distinct meshes built so far.
+    pub fn len(&self) -> usize {
+        self.meshes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty()
+    }
+    /// Forgets every cached mesh, they are freed once no decal uses them anymore.
+    pub fn clear(&mut self) {
+        self.meshes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::mesh::VertexAttributeValues;
+
+    use super::*;
+
+    fn positions(mesh: &Mesh) -> Vec<Vec3> {
+        match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) => {
+                positions.iter().copied().map(Vec3::from).collect()
+            }
+            _ => panic!("quad should have positions"),
+        }
+    }
+
+    fn uvs(mesh: &Mesh) -> Vec<Vec2> {
+        match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => {
+                uvs.iter().copied().map(Vec2::from).collect()
+            }
+            _ => panic!("quad should have uvs"),
+        }
+    }
+
+    #[test]
+    fn quad_lies_in_the_plane_of_its_normal() {
+        let normal = Vec3::new(1.0, 2.0, -0.5);
+        let mesh = DecalQuadBuilder::new(normal)
+            .size(Vec2::new(2.0, 3.0))
+            .build()
+            .unwrap();
+        for position in positions(&mesh) {
+            assert!(position.dot(normal).abs() < 1e-5);
+        }
+        assert!(mesh.attribute(Mesh::ATTRIBUTE_TANGENT).is_some());
+    }
+
+    #[test]
+    fn pivot_moves_the_quad_off_the_origin() {
+        let mesh = DecalQuadBuilder::new(Vec3::Z)
+            .up(Vec3::Y)
+            .size(Vec2::new(2.0, 4.0))
+            .pivot(Vec2::new(-0.5, -0.5))
+            .build()
+            .unwrap();
+        let positions = positions(&mesh);
+        let min = positions.iter().copied().fold(Vec3::MAX, Vec3::min);
+        let max = positions.iter().copied().fold(Vec3::MIN, Vec3::max);
+        assert!(min.abs_diff_eq(Vec3::ZERO, 1e-5));
+        assert!(max.abs_diff_eq(Vec3::new(2.0, 4.0, 0.0), 1e-5));
+    }
+
+    #[test]
+    fn up_points_to_the_top_of_the_texture() {
+        let mesh = DecalQuadBuilder::new(Vec3::Y)
+            .up(Vec3::new(1.0, 5.0, 0.0))
+            .build()
+            .unwrap();
+        for (position, uv) in positions(&mesh).into_iter().zip(uvs(&mesh)) {
+            // The non-perpendicular part of `up` is rejected, leaving +x as the top edge.
+            assert_eq!(position.x > 0.0, uv.y < 0.5);
+        }
+    }
+
+    #[test]
+    fn flips_mirror_the_uv_rect() {
+        let rect = Rect::new(0.25, 0.5, 0.75, 1.0);
+        let quad = DecalQuadBuilder::new(Vec3::Y).uv_rect(rect);
+        let uvs_plain = uvs(&quad.build().unwrap());
+        let uvs_flipped = uvs(&quad.flip_x(true).flip_y(true).build().unwrap());
+        for (plain, flipped) in uvs_plain.into_iter().zip(uvs_flipped) {
+            assert!(rect.contains(plain) && rect.contains(flipped));
+            assert!((plain + flipped).abs_diff_eq(rect.min + rect.max, 1e-6));
+        }
+    }
+
+    #[test]
+    fn invalid_inputs_are_reported() {
+        assert!(matches!(
+            DecalQuadBuilder::new(Vec3::ZERO).build(),
+            Err(DecalMeshError::InvalidNormal(_))
+        ));
+        assert!(matches!(
+            DecalQuadBuilder::new(Vec3::NAN).build(),
+            Err(DecalMeshError::InvalidNormal(_))
+        ));
+        assert!(matches!(
+            DecalQuadBuilder::new(Vec3::Y).up(Vec3::NEG_Y * 2.0).build(),
+            Err(DecalMeshError::InvalidUp(_))
+        ));
+        assert!(matches!(
+            DecalQuadBuilder::new(Vec3::Y)
+                .size(Vec2::new(1.0, 0.0))
+                .build(),
+            Err(DecalMeshError::InvalidSize(_))
+        ));
+        assert!(matches!(
+            DecalQuadBuilder::new(Vec3::Y)
+                .size(Vec2::new(f32::INFINITY, 1.0))
+                .build(),
+            Err(DecalMeshError::InvalidSize(_))
+        ));
+    }
+
+    #[test]
+    fn equal_builders_share_a_cached_mesh() {
+        let mut cache = DecalMeshCache::default();
+        let mut meshes = Assets::<Mesh>::default();
+        let quad = DecalQuadBuilder::new(Vec3::Y).size(Vec2::splat(2.0));
+        let first = cache.get_or_insert(quad, &mut meshes).unwrap();
+        let second = cache.get_or_insert(quad, &mut meshes).unwrap();
+        assert_eq!(first, second);
+        cache.get_or_insert(quad.flip_x(true), &mut meshes).unwrap();
+        assert_eq!(cache.len(), 2);
+    }
+}
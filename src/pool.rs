@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{
+    layer::DecalSequence, Decal, DecalAnimation, DecalFlipbook, DecalInstanceParams, DecalLayer,
+    DecalLifetime,
+};
+
+/// Reuses decal entities instead of spawning new ones, grouped into categories that each hold
+/// up to a set number of decals. Once a category is full the oldest decal in it is recycled.
+///
+/// ```rs
+/// fn setup(mut pool: ResMut<DecalPool>) {
+///     pool.set_capacity("bullet_holes", 500);
+/// }
+///
+/// fn shoot(mut commands: Commands, mut pool: ResMut<DecalPool>, handles: Res<MyHandles>) {
+///     pool.place(
+///         &mut commands,
+///         "bullet_holes",
+///         (Decal::new(handles.bullet_hole.clone()), Transform::from_translation(hit)),
+///     );
+/// }
+/// ```
+#[derive(Resource)]
+pub struct DecalPool {
+    /// Capacity of categories without one set through [`DecalPool::set_capacity`].
+    pub default_capacity: usize,
+    categories: HashMap<String, PoolCategory>,
+}
+impl Default for DecalPool {
+    fn default() -> Self {
+        Self {
+            default_capacity: 256,
+            categories: HashMap::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct PoolCategory {
+    capacity: Option<usize>,
+    /// Entities from oldest to newest.
+    entities: VecDeque<Entity>,
+}
+
+impl DecalPool {
+    /// Sets how many decals `category` holds. If it currently holds more, the oldest ones are
+    /// despawned on the next [`DecalPool::place`].
+    pub fn set_capacity(&mut self, category: impl Into<String>, capacity: usize) {
+        self.categories.entry(category.into()).or_default().capacity = Some(capacity);
+    }
+    pub fn capacity(&self, category: &str) -> usize {
+        self.categories
+            .get(category)
+            .and_then(|category| category.capacity)
+            .unwrap_or(self.default_capacity)
+    }
+    /// Number of decals currently in `category`.
+    pub fn len(&self, category: &str) -> usize {
+        self.categories
+            .get(category)
+            .map_or(0, |category| category.entities.len())
+    }
+    pub fn is_empty(&self, category: &str) -> bool {
+        self.len(category) == 0
+    }
+
+    /// Places a decal in `category`, `bundle` is usually a [`Decal`] and a [`Transform`].
+    ///
+    /// While the category has room a new entity is spawned, otherwise its oldest decal is moved to
+    /// the end of the line and `bundle` is inserted on it, after removing its [`DecalLifetime`],
+    /// [`DecalFlipbook`], [`DecalAnimation`], [`DecalInstanceParams`] and [`DecalLayer`] so they
+    /// don't carry over. A recycled decal draws on top of the others in its layer like a new one,
+    /// and counts as the newest decal for
+    /// [`DecalSettings::max_decals`](crate::DecalSettings::max_decals).
+    pub fn place(
+        &mut self,
+        commands: &mut Commands,
+        category: impl Into<String>,
+        bundle: impl Bundle,
+    ) -> Entity {
+        let default_capacity = self.default_capacity;
+        let category = self.categories.entry(category.into()).or_default();
+        let capacity = category.capacity.unwrap_or(default_capacity).max(1);
+        while category.entities.len() > capacity {
+            if let Some(oldest) = category.entities.pop_front()
+                && let Ok(mut oldest) = commands.get_entity(oldest)
+            {
+                oldest.try_despawn();
+            }
+        }
+        let mut oldest = None;
+        while oldest.is_none() && category.entities.len() == capacity {
+            let entity = category.entities.pop_front().unwrap();
+            // Decals despawned since the pool was last pruned only free up their spot.
+            oldest = commands.get_entity(entity).is_ok().then_some(entity);
+        }
+        let entity = match oldest {
+            Some(oldest) => {
+                commands
+                    .entity(oldest)
+                    .try_remove::<(
+                        DecalLifetime,
+                        DecalFlipbook,
+                        DecalAnimation,
+                        DecalInstanceParams,
+                        DecalLayer,
+                        DecalSequence,
+                    )>()
+                    .try_insert(bundle);
+                oldest
+            }
+            None => commands.spawn(bundle).id(),
+        };
+        category.entities.push_back(entity);
+        entity
+    }
+
+    fn forget(&mut self, entity: Entity) {
+        for category in self.categories.values_mut() {
+            category.entities.retain(|pooled| *pooled != entity);
+        }
+    }
+}
+
+/// Forgets pooled decals that were despawned by something else, like a [`DecalLifetime`].
+pub(crate) fn prune_decal_pool(mut removed: RemovedComponents<Decal>, mut pool: ResMut<DecalPool>) {
+    for entity in removed.read() {
+        pool.forget(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component)]
+    struct Marker(u32);
+
+    fn place(world: &mut World, pool: &mut DecalPool, category: &str, marker: u32) -> Entity {
+        let entity = pool.place(&mut world.commands(), category, Marker(marker));
+        world.flush();
+        entity
+    }
+
+    #[test]
+    fn full_categories_recycle_their_oldest_decal() {
+        let mut world = World::new();
+        let mut pool = DecalPool::default();
+        pool.set_capacity("holes", 2);
+        let first = place(&mut world, &mut pool, "holes", 0);
+        let second = place(&mut world, &mut pool, "holes", 1);
+        world.entity_mut(first).insert(DecalLayer(3));
+
+        assert_eq!(place(&mut world, &mut pool, "holes", 2), first);
+        assert_eq!(world.get::<Marker>(first).unwrap().0, 2);
+        assert!(world.get::<DecalLayer>(first).is_none());
+        assert_eq!(place(&mut world, &mut pool, "holes", 3), second);
+        assert_eq!(pool.len("holes"), 2);
+        assert_eq!(pool.len("other"), 0);
+    }
+
+    #[test]
+    fn categories_use_the_default_capacity() {
+        let mut world = World::new();
+        let mut pool = DecalPool {
+            default_capacity: 1,
+            ..default()
+        };
+        pool.set_capacity("big", 3);
+        assert_eq!(pool.capacity("small"), 1);
+        assert_eq!(pool.capacity("big"), 3);
+        let first = place(&mut world, &mut pool, "small", 0);
+        assert_eq!(place(&mut world, &mut pool, "small", 1), first);
+        place(&mut world, &mut pool, "big", 0);
+        assert_ne!(place(&mut world, &mut pool, "big", 1), first);
+    }
+
+    #[test]
+    fn shrinking_despawns_the_oldest_decals() {
+        let mut world = World::new();
+        let mut pool = DecalPool::default();
+        let placed: Vec<Entity> = (0..4)
+            .map(|marker| place(&mut world, &mut pool, "holes", marker))
+            .collect();
+        pool.set_capacity("holes", 2);
+        let recycled = place(&mut world, &mut pool, "holes", 4);
+        assert_eq!(recycled, placed[2]);
+        assert!(world.get_entity(placed[0]).is_err());
+        assert!(world.get_entity(placed[1]).is_err());
+        assert_eq!(pool.len("holes"), 2);
+    }
+
+    #[test]
+    fn despawned_decals_are_not_recycled() {
+        let mut world = World::new();
+        let mut pool = DecalPool::default();
+        pool.set_capacity("holes", 2);
+        let first = place(&mut world, &mut pool, "holes", 0);
+        let second = place(&mut world, &mut pool, "holes", 1);
+        // Despawned by something else before the pool was pruned.
+        world.despawn(first);
+
+        let third = place(&mut world, &mut pool, "holes", 2);
+        assert_ne!(third, second);
+        assert_eq!(world.get::<Marker>(third).unwrap().0, 2);
+        assert_eq!(world.get::<Marker>(second).unwrap().0, 1);
+        assert_eq!(pool.len("holes"), 2);
+        assert_eq!(place(&mut world, &mut pool, "holes", 3), second);
+    }
+}
@@ -0,0 +1,29 @@
+use bevy::{core_pipeline::prepass::DepthPrepass, platform::collections::HashSet, prelude::*};
+
+use crate::{Decal, DecalSettings};
+
+/// Decals read the depth prepass, so while any exist every [`Camera3d`] needs a [`DepthPrepass`],
+/// including cameras with a [`DeferredPrepass`](bevy::core_pipeline::prepass::DeferredPrepass)
+/// whose G-buffer they read normals from. Depending on [`DecalSettings::auto_depth_prepass`] it is
+/// either added or warned about once per camera.
+pub(crate) fn manage_depth_prepass(
+    mut commands: Commands,
+    cameras: Query<Entity, (With<Camera3d>, Without<DepthPrepass>)>,
+    decals: Query<(), With<Decal>>,
+    settings: Res<DecalSettings>,
+    mut warned: Local<HashSet<Entity>>,
+) {
+    if decals.is_empty() {
+        return;
+    }
+    for camera in &cameras {
+        if settings.auto_depth_prepass {
+            commands.entity(camera).insert(DepthPrepass);
+        } else if warned.insert(camera) {
+            warn!(
+                "Camera {camera} has no DepthPrepass, decals will not render correctly in it. \
+                 Add DepthPrepass to the camera or enable DecalPlugin::auto_depth_prepass."
+            );
+        }
+    }
+}
@@ -0,0 +1,361 @@
+use bevy::{
+    asset::{weak_handle, RenderAssetUsages},
+    core_pipeline::prepass::{DeferredPrepass, DepthPrepass, MotionVectorPrepass, NormalPrepass},
+    ecs::query::QueryItem,
+    math::FloatOrd,
+    pbr::{
+        DrawMesh, MeshPipeline, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup,
+        SetMeshViewBindGroup,
+    },
+    platform::collections::HashSet,
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        mesh::{MeshVertexBufferLayoutRef, RenderMesh},
+        render_asset::RenderAssets,
+        render_graph::{NodeRunError, RenderGraphContext, RenderLabel, ViewNode},
+        render_phase::{
+            CachedRenderPipelinePhaseItem, DrawFunctionId, DrawFunctions, PhaseItem,
+            PhaseItemExtraIndex, SetItemPipeline, SortedPhaseItem, ViewSortedRenderPhases,
+        },
+        render_resource::{
+            CachedRenderPipelineId, ColorTargetState, ColorWrites, Extent3d, LoadOp,
+            MultisampleState, Operations, PipelineCache, RenderPassColorAttachment,
+            RenderPassDescriptor, RenderPipelineDescriptor, ShaderDefVal, SpecializedMeshPipeline,
+            SpecializedMeshPipelineError, SpecializedMeshPipelines, StoreOp, TextureDimension,
+            TextureFormat, TextureUsages,
+        },
+        renderer::RenderContext,
+        sync_world::MainEntity,
+        texture::GpuImage,
+        view::{ExtractedView, RenderVisibleEntities, RetainedViewEntity},
+        Extract,
+    },
+};
+use std::{
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Texture holding the [`DecalReceiver`] groups of the surface under every pixel, shared by every
+/// decal material and redrawn before the main pass of each camera with receivers in view.
+///
+/// It is allocated once with
+/// [`DecalPlugin::receiver_mask_size`](crate::DecalPlugin::receiver_mask_size) texels, the view of
+/// every camera is stretched over all of it.
+pub const DECAL_RECEIVER_MASK_HANDLE: Handle<Image> =
+    weak_handle!("9d706c6a-2ebf-4d7f-8140-8eab1d808906");
+
+/// Puts a mesh in decal receiver groups, a bit mask matched against
+/// [`DecalInstanceParams::receivers`](crate::DecalInstanceParams::receivers). Decals only project
+/// onto surfaces sharing at least one group with them.
+///
+/// Meshes without one are in [`DecalReceiver::DEFAULT`]. Receivers are read from the depth
+/// prepass, so meshes that don't write depth there can't be told apart from what is behind them.
+#[derive(Component, ExtractComponent, Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[reflect(Component, Default)]
+pub struct DecalReceiver(pub u32);
+impl DecalReceiver {
+    /// Group of meshes without a [`DecalReceiver`].
+    pub const DEFAULT: u32 = 1;
+}
+impl Default for DecalReceiver {
+    fn default() -> Self {
+        Self(Self::DEFAULT)
+    }
+}
+
+/// Keeps every decal off a mesh, the same as a [`DecalReceiver`] without groups.
+#[derive(Component, ExtractComponent, Reflect, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct NoDecals;
+
+/// The receiver mask, filled with [`DecalReceiver::DEFAULT`].
+pub(crate) fn decal_receiver_mask(size: UVec2) -> Image {
+    let size = size.max(UVec2::ONE);
+    let mut mask = Image::new_fill(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &DecalReceiver::DEFAULT.to_le_bytes(),
+        TextureFormat::R32Uint,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    mask.texture_descriptor.usage |= TextureUsages::RENDER_ATTACHMENT;
+    mask
+}
+
+/// Whether the receiver mask holds anything but [`DecalReceiver::DEFAULT`], in the render world.
+#[derive(Resource, Default)]
+pub(crate) struct DecalReceiverMaskDirty(AtomicBool);
+
+/// Receivers drawn into the mask for one view, only meshes outside the default group are drawn.
+pub(crate) struct DecalReceiver3d {
+    pub distance: f32,
+    pub entity: (Entity, MainEntity),
+    pub pipeline: CachedRenderPipelineId,
+    pub draw_function: DrawFunctionId,
+    pub batch_range: Range<u32>,
+    pub extra_index: PhaseItemExtraIndex,
+    pub indexed: bool,
+}
+impl PhaseItem for DecalReceiver3d {
+    fn entity(&self) -> Entity {
+        self.entity.0
+    }
+    fn main_entity(&self) -> MainEntity {
+        self.entity.1
+    }
+    fn draw_function(&self) -> DrawFunctionId {
+        self.draw_function
+    }
+    fn batch_range(&self) -> &Range<u32> {
+        &self.batch_range
+    }
+    fn batch_range_mut(&mut self) -> &mut Range<u32> {
+        &mut self.batch_range
+    }
+    fn extra_index(&self) -> PhaseItemExtraIndex {
+        self.extra_index.clone()
+    }
+    fn batch_range_and_extra_index_mut(&mut self) -> (&mut Range<u32>, &mut PhaseItemExtraIndex) {
+        (&mut self.batch_range, &mut self.extra_index)
+    }
+}
+impl SortedPhaseItem for DecalReceiver3d {
+    type SortKey = FloatOrd;
+    fn sort_key(&self) -> Self::SortKey {
+        FloatOrd(self.distance)
+    }
+    fn indexed(&self) -> bool {
+        self.indexed
+    }
+}
+impl CachedRenderPipelinePhaseItem for DecalReceiver3d {
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.pipeline
+    }
+}
+
+pub(crate) type DrawDecalReceiver = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    DrawMesh,
+);
+
+/// Bevy's mesh pipeline with a fragment shader writing receiver groups instead of color.
+#[derive(Resource)]
+pub(crate) struct DecalReceiverPipeline {
+    mesh_pipeline: MeshPipeline,
+    shader: Handle<Shader>,
+    mask_size: UVec2,
+}
+impl DecalReceiverPipeline {
+    pub(crate) fn new(world: &mut World, mask_size: UVec2) -> Self {
+        Self {
+            mesh_pipeline: MeshPipeline::from_world(world),
+            shader: world
+                .resource::<AssetServer>()
+                .load("embedded://bevy_contact_projective_decals/decal_receiver.wgsl"),
+            mask_size: mask_size.max(UVec2::ONE),
+        }
+    }
+}
+impl SpecializedMeshPipeline for DecalReceiverPipeline {
+    /// Mesh key and the receiver groups written by the pipeline.
+    type Key = (MeshPipelineKey, u32);
+
+    fn specialize(
+        &self,
+        (key, groups): Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+        descriptor.label = Some("decal_receiver_pipeline".into());
+        let mut shader_defs = descriptor.vertex.shader_defs.clone();
+        shader_defs.extend([
+            ShaderDefVal::UInt("DECAL_RECEIVER_GROUPS".into(), groups),
+            ShaderDefVal::UInt("DECAL_RECEIVER_MASK_WIDTH".into(), self.mask_size.x),
+            ShaderDefVal::UInt("DECAL_RECEIVER_MASK_HEIGHT".into(), self.mask_size.y),
+        ]);
+        let Some(fragment) = &mut descriptor.fragment else {
+            return Ok(descriptor);
+        };
+        fragment.shader = self.shader.clone();
+        fragment.shader_defs = shader_defs;
+        fragment.targets = vec![Some(ColorTargetState {
+            format: TextureFormat::R32Uint,
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        })];
+        // Occlusion is resolved against the depth prepass in the shader instead.
+        descriptor.depth_stencil = None;
+        descriptor.multisample = MultisampleState::default();
+        Ok(descriptor)
+    }
+}
+
+pub(crate) fn extract_decal_receiver_phases(
+    mut phases: ResMut<ViewSortedRenderPhases<DecalReceiver3d>>,
+    cameras: Extract<Query<(Entity, &Camera), With<DepthPrepass>>>,
+    mut live: Local<HashSet<RetainedViewEntity>>,
+) {
+    live.clear();
+    for (entity, camera) in &cameras {
+        if !camera.is_active {
+            continue;
+        }
+        let view = RetainedViewEntity::new(entity.into(), None, 0);
+        phases.insert_or_clear(view);
+        live.insert(view);
+    }
+    phases.retain(|view, _| live.contains(view));
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn queue_decal_receivers(
+    draw_functions: Res<DrawFunctions<DecalReceiver3d>>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<DecalReceiverPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    receiver_pipeline: Res<DecalReceiverPipeline>,
+    render_meshes: Res<RenderAssets<RenderMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    mut phases: ResMut<ViewSortedRenderPhases<DecalReceiver3d>>,
+    views: Query<(
+        &ExtractedView,
+        &RenderVisibleEntities,
+        &Msaa,
+        Has<NormalPrepass>,
+        Has<MotionVectorPrepass>,
+        Has<DeferredPrepass>,
+    )>,
+    receivers: Query<(Option<&DecalReceiver>, Has<NoDecals>)>,
+) {
+    let draw_function = draw_functions.read().id::<DrawDecalReceiver>();
+    for (view, visible_entities, msaa, normal_prepass, motion_vector_prepass, deferred_prepass) in
+        &views
+    {
+        let Some(phase) = phases.get_mut(&view.retained_view_entity) else {
+            continue;
+        };
+        let mut view_key = MeshPipelineKey::from_msaa_samples(msaa.samples())
+            | MeshPipelineKey::from_hdr(view.hdr)
+            | MeshPipelineKey::DEPTH_PREPASS;
+        view_key.set(MeshPipelineKey::NORMAL_PREPASS, normal_prepass);
+        view_key.set(
+            MeshPipelineKey::MOTION_VECTOR_PREPASS,
+            motion_vector_prepass,
+        );
+        view_key.set(MeshPipelineKey::DEFERRED_PREPASS, deferred_prepass);
+        let rangefinder = view.rangefinder3d();
+
+        for (render_entity, visible_entity) in visible_entities.iter::<Mesh3d>() {
+            let Ok((receiver, no_decals)) = receivers.get(*render_entity) else {
+                continue;
+            };
+            let groups = match (receiver, no_decals) {
+                (_, true) => 0,
+                (Some(receiver), false) => receiver.0,
+                (None, false) => continue,
+            };
+            if groups == DecalReceiver::DEFAULT {
+                continue;
+            }
+            let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(*visible_entity)
+            else {
+                continue;
+            };
+            let Some(mesh) = render_meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+            let key = view_key
+                | MeshPipelineKey::from_bits_retain(mesh.key_bits.bits())
+                | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology());
+            let pipeline = match pipelines.specialize(
+                &pipeline_cache,
+                &receiver_pipeline,
+                (key, groups),
+                &mesh.layout,
+            ) {
+                Ok(pipeline) => pipeline,
+                Err(error) => {
+                    error!("{error}");
+                    continue;
+                }
+            };
+            phase.add(DecalReceiver3d {
+                distance: rangefinder.distance_translation(&mesh_instance.translation),
+                entity: (*render_entity, *visible_entity),
+                pipeline,
+                draw_function,
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::None,
+                indexed: mesh.indexed(),
+            });
+        }
+    }
+}
+
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+pub(crate) struct DecalReceiverPass;
+
+/// Clears the receiver mask to [`DecalReceiver::DEFAULT`] and draws the view's receivers into it.
+///
+/// Views without receivers to draw skip the pass, and only clear the mask if an earlier view left
+/// receivers in it.
+#[derive(Default)]
+pub(crate) struct DecalReceiverNode;
+impl ViewNode for DecalReceiverNode {
+    type ViewQuery = &'static ExtractedView;
+
+    fn run<'w>(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        view: QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let phase = world
+            .resource::<ViewSortedRenderPhases<DecalReceiver3d>>()
+            .get(&view.retained_view_entity)
+            .filter(|phase| !phase.items.is_empty());
+        let dirty = &world.resource::<DecalReceiverMaskDirty>().0;
+        if phase.is_none() && !dirty.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let Some(mask) = world
+            .resource::<RenderAssets<GpuImage>>()
+            .get(DECAL_RECEIVER_MASK_HANDLE.id())
+        else {
+            return Ok(());
+        };
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("decal_receiver_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &mask.texture_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(
+                        LinearRgba::new(DecalReceiver::DEFAULT as f32, 0.0, 0.0, 0.0).into(),
+                    ),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        dirty.store(phase.is_some(), Ordering::Relaxed);
+        if let Some(phase) = phase
+            && let Err(error) = phase.render(&mut render_pass, world, graph.view_entity())
+        {
+            error!("Error encountered while rendering the decal receiver phase {error:?}");
+        }
+        Ok(())
+    }
+}
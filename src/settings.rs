@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+
+use bevy::{pbr::NotShadowReceiver, prelude::*};
+
+use crate::{layer::DecalSequence, Decal};
+
+/// Fade factor of [`DecalSettings::default`].
+pub(crate) const DEFAULT_FADE_FACTOR: f32 = 8.0;
+
+/// Project-wide decal defaults, inserted by [`DecalPlugin`](crate::DecalPlugin) from its fields.
+/// Changes to the resource apply to existing decals too.
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct DecalSettings {
+    /// Fade factor of decals whose material doesn't specify one, see
+    /// [`DecalMaterial::depth_fade_factor`](crate::DecalMaterial::depth_fade_factor). Decals
+    /// drawn with a bare [`DecalMeshMaterial3d`](crate::DecalMeshMaterial3d) and no [`Decal`] use
+    /// the default of 8.0 instead.
+    pub default_fade_factor: f32,
+    /// Adds a [`DepthPrepass`](bevy::core_pipeline::prepass::DepthPrepass) to every [`Camera3d`]
+    /// missing one while decals exist, instead of only warning about it.
+    pub auto_depth_prepass: bool,
+    /// Lets decals receive shadows from other geometry.
+    pub shadows: bool,
+    /// Despawns the oldest decals once more than this many exist.
+    pub max_decals: Option<usize>,
+}
+impl Default for DecalSettings {
+    fn default() -> Self {
+        Self {
+            default_fade_factor: DEFAULT_FADE_FACTOR,
+            auto_depth_prepass: false,
+            shadows: false,
+            max_decals: None,
+        }
+    }
+}
+
+/// Decal entities from oldest to newest, with the [`DecalSequence`] they were placed with. Decals
+/// recycled by the [`DecalPool`](crate::DecalPool) get a new one and are queued again at the end.
+#[derive(Resource, Default)]
+pub(crate) struct DecalSpawnOrder(VecDeque<(Entity, u64)>);
+
+pub(crate) fn apply_decal_shadow_settings(
+    mut commands: Commands,
+    decals: Query<(Entity, Ref<Decal>)>,
+    settings: Res<DecalSettings>,
+) {
+    // New decals already come without shadows, so only toggling the setting touches them all.
+    if !settings.shadows && !settings.is_changed() {
+        return;
+    }
+    for (entity, decal) in &decals {
+        if !decal.is_added() && !settings.is_changed() {
+            continue;
+        }
+        if settings.shadows {
+            commands.entity(entity).remove::<NotShadowReceiver>();
+        } else {
+            commands.entity(entity).insert(NotShadowReceiver);
+        }
+    }
+}
+
+pub(crate) fn enforce_max_decals(
+    mut commands: Commands,
+    placed: Query<(Entity, &DecalSequence), Added<DecalSequence>>,
+    decals: Query<Option<&DecalSequence>, With<Decal>>,
+    mut removed: RemovedComponents<Decal>,
+    mut order: ResMut<DecalSpawnOrder>,
+    settings: Res<DecalSettings>,
+) {
+    let removed: Vec<Entity> = removed.read().collect();
+    if !removed.is_empty() {
+        order.0.retain(|(entity, _)| !removed.contains(entity));
+    }
+    order
+        .0
+        .extend(placed.iter().map(|(entity, sequence)| (entity, sequence.0)));
+    let is_current = |&(entity, sequence): &(Entity, u64)| {
+        decals
+            .get(entity)
+            .is_ok_and(|current| current.is_some_and(|current| current.0 == sequence))
+    };
+    // Entries left behind by recycled decals are skipped below, drop them before they pile up.
+    if order.0.len() > 2 * decals.iter().len() {
+        order.0.retain(is_current);
+    }
+
+    let Some(max_decals) = settings.max_decals else {
+        return;
+    };
+    let mut excess = decals.iter().len().saturating_sub(max_decals);
+    while excess > 0 {
+        let Some(oldest) = order.0.pop_front() else {
+            break;
+        };
+        if is_current(&oldest) {
+            commands.entity(oldest.0).try_despawn();
+            excess -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recycled_decals_count_as_the_newest() {
+        let mut world = World::new();
+        world.init_resource::<DecalSpawnOrder>();
+        world.insert_resource(DecalSettings {
+            max_decals: Some(2),
+            ..default()
+        });
+        let mut schedule = Schedule::default();
+        schedule.add_systems(enforce_max_decals);
+        let first = world.spawn((Decal::default(), DecalSequence(0))).id();
+        let second = world.spawn((Decal::default(), DecalSequence(1))).id();
+        schedule.run(&mut world);
+
+        // Placed again by the pool, then a third decal pushes one out.
+        world
+            .entity_mut(first)
+            .remove::<DecalSequence>()
+            .insert(DecalSequence(2));
+        world.spawn((Decal::default(), DecalSequence(3)));
+        schedule.run(&mut world);
+        assert!(world.get_entity(first).is_ok());
+        assert!(world.get_entity(second).is_err());
+    }
+}
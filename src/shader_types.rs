@@ -0,0 +1,56 @@
+use bevy::{prelude::*, render::render_resource::ShaderType};
+
+/// The GPU representation of a [`DecalMaterial`](crate::DecalMaterial).
+#[derive(ShaderType, Clone, Debug)]
+pub struct DecalMaterialUniform {
+    pub depth_fade_factor: f32,
+    /// Atlas region as min xy and size zw.
+    pub atlas_rect: Vec4,
+    /// Columns and rows of a [`DecalAtlas::Grid`](crate::DecalAtlas::Grid), zero otherwise.
+    pub atlas_grid: UVec2,
+    /// Cosines of the view angles where the grazing fade starts and ends.
+    pub angle_fade: Vec2,
+    /// 0 for no edge mask, 1 for a rectangular and 2 for an elliptical one.
+    pub edge_mask: u32,
+    /// Width of the edge mask's falloff in uv units.
+    pub edge_softness: f32,
+    /// 0 linear, 1 smoothstep, 2 exponential and 3 power depth fade.
+    pub fade_curve: u32,
+    /// Exponent of the power depth fade.
+    pub fade_power: f32,
+    /// Fade factor behind the decal plane, negative to use `depth_fade_factor`.
+    pub back_fade_factor: f32,
+    /// Furthest a painted surface may be from the decal plane, in world units.
+    pub max_plane_distance: f32,
+    /// Furthest a painted surface may be from the decal in view depth, in world units.
+    pub max_view_distance: f32,
+}
+
+/// Per-entity decal data, indexed by the decal's [`MeshTag`](bevy::render::mesh::MeshTag).
+#[derive(ShaderType, Clone, Copy, Debug, PartialEq)]
+pub struct DecalInstance {
+    /// Atlas region overriding the material's, as min xy and size zw. Zero size keeps the
+    /// material's.
+    pub atlas_rect: Vec4,
+    /// Linear color multiplied onto the base color.
+    pub tint: Vec4,
+    pub opacity: f32,
+    /// Overrides the material's fade factor unless negative.
+    pub depth_fade_factor: f32,
+    /// Cell of the material's atlas grid to show instead of its own, unless `u32::MAX`.
+    pub atlas_index: u32,
+    /// Receiver groups the decal projects onto.
+    pub receivers: u32,
+}
+impl Default for DecalInstance {
+    fn default() -> Self {
+        Self {
+            atlas_rect: Vec4::ZERO,
+            tint: Vec4::ONE,
+            opacity: 1.0,
+            depth_fade_factor: -1.0,
+            atlas_index: u32::MAX,
+            receivers: u32::MAX,
+        }
+    }
+}